@@ -0,0 +1,115 @@
+//! 对话列表查询基准测试
+//!
+//! 运行: `cargo bench --bench conversations`
+
+use entity::{conversation, message, student};
+use mtp_tauri_lib::{dataset, db};
+use sea_orm::{ActiveModelTrait, Set};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const CONVERSATION_COUNT: usize = 300;
+const MESSAGES_PER_CONVERSATION: i32 = 50;
+const ITERATIONS: u32 = 20;
+
+#[tokio::main]
+async fn main() {
+    let data_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("data");
+    let work_dir = std::env::temp_dir().join(format!("mtp-bench-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&work_dir).expect("无法创建临时目录");
+
+    let dataset_paths = dataset::DatasetPaths {
+        manifest: data_dir.join("manifest.json"),
+        students_content: data_dir.join("students.json"),
+        students_data: data_dir.join("students_min.json"),
+    };
+    let (client, _) = db::init_db(work_dir.join("db.sqlite"), &dataset_paths)
+        .await
+        .expect("Failed to initialize database");
+
+    seed(&client).await;
+
+    // 预热
+    let total = db::get_conversations(&client).await.unwrap().len();
+
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        db::get_conversations(&client).await.unwrap();
+        elapsed += start.elapsed();
+    }
+
+    println!(
+        "get_conversations: {} 个对话, 平均耗时 {:?} ({} 次)",
+        total,
+        elapsed / ITERATIONS,
+        ITERATIONS
+    );
+
+    drop(client);
+    let _ = std::fs::remove_dir_all(&work_dir);
+}
+
+// 生成测试用的学生、对话和消息
+async fn seed(client: &db::DbClient) {
+    let conn = client.lock().await;
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+
+    for i in 0..CONVERSATION_COUNT {
+        let name = format!("bench-student-{}", i);
+
+        student::ActiveModel {
+            id: Default::default(),
+            name: Set(name.clone()),
+            avatars: Set("[]".to_string()),
+            prompt: Set(String::new()),
+            is_custom: Set(true),
+            metadata: Set("{}".to_string()),
+            content: Set(String::new()),
+            prompt_template_id: Set(None),
+            avatar_labels: Set("{}".to_string()),
+            typing_speed: Set(None),
+        }
+        .insert(&*conn)
+        .await
+        .unwrap();
+
+        let conversation_id = uuid::Uuid::new_v4().to_string();
+        conversation::ActiveModel {
+            id: Set(conversation_id.clone()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            title: Set(name.clone()),
+            student_name: Set(name),
+            last_read_index: Set(MESSAGES_PER_CONVERSATION / 2),
+            prompt_override: Set(None),
+            avatar: Set(None),
+            persona_id: Set(None),
+            title_locked: Set(false),
+        }
+        .insert(&*conn)
+        .await
+        .unwrap();
+
+        for index in 0..MESSAGES_PER_CONVERSATION {
+            let role = if index % 2 == 0 { "user" } else { "assistant" };
+            message::ActiveModel {
+                id: Default::default(),
+                conversation_id: Set(conversation_id.clone()),
+                role: Set(role.to_string()),
+                content: Set(format!("message {}", index)),
+                name: Set(String::new()),
+                created_at: Set(now),
+                index: Set(index),
+                prompt_revision_id: Set(None),
+                proactive: Set(false),
+                turn_id: Set(None),
+                read_at: Set(None),
+                delivered_at: Set(None),
+            }
+            .insert(&*conn)
+            .await
+            .unwrap();
+        }
+    }
+}
@@ -13,6 +13,12 @@ pub struct Model {
     pub title: String,
     #[sea_orm(unique)]
     pub student_name: String,
+    pub last_read_index: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub prompt_override: Option<String>,
+    pub avatar: Option<String>,
+    pub persona_id: Option<i32>,
+    pub title_locked: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,6 +2,18 @@
 
 pub mod prelude;
 
+pub mod anniversary;
+pub mod app_meta;
 pub mod conversation;
+pub mod embedding;
+pub mod lore_chunk;
+pub mod lorebook;
+pub mod lorebook_entry;
 pub mod message;
+pub mod persona;
+pub mod prompt_template;
 pub mod student;
+pub mod student_bond;
+pub mod student_memory;
+pub mod student_profile;
+pub mod student_prompt_revision;
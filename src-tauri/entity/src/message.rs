@@ -15,6 +15,11 @@ pub struct Model {
     pub name: String,
     pub created_at: DateTimeWithTimeZone,
     pub index: i32,
+    pub prompt_revision_id: Option<i32>,
+    pub proactive: bool,
+    pub turn_id: Option<String>,
+    pub read_at: Option<DateTimeWithTimeZone>,
+    pub delivered_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,5 +1,17 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.7
 
+pub use super::anniversary::Entity as Anniversary;
+pub use super::app_meta::Entity as AppMeta;
 pub use super::conversation::Entity as Conversation;
+pub use super::embedding::Entity as Embedding;
+pub use super::lore_chunk::Entity as LoreChunk;
+pub use super::lorebook::Entity as Lorebook;
+pub use super::lorebook_entry::Entity as LorebookEntry;
 pub use super::message::Entity as Message;
+pub use super::persona::Entity as Persona;
+pub use super::prompt_template::Entity as PromptTemplate;
 pub use super::student::Entity as Student;
+pub use super::student_bond::Entity as StudentBond;
+pub use super::student_memory::Entity as StudentMemory;
+pub use super::student_profile::Entity as StudentProfile;
+pub use super::student_prompt_revision::Entity as StudentPromptRevision;
@@ -12,12 +12,41 @@ pub struct Model {
     pub name: String,
     pub avatars: String,
     pub prompt: String,
+    pub is_custom: bool,
+    #[sea_orm(column_type = "Text")]
+    pub metadata: String,
+    #[sea_orm(column_type = "Text")]
+    pub content: String,
+    pub prompt_template_id: Option<i32>,
+    #[sea_orm(column_type = "Text")]
+    pub avatar_labels: String,
+    pub typing_speed: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::anniversary::Entity")]
+    Anniversary,
     #[sea_orm(has_one = "super::conversation::Entity")]
     Conversation,
+    #[sea_orm(has_many = "super::lore_chunk::Entity")]
+    LoreChunk,
+    #[sea_orm(has_many = "super::lorebook::Entity")]
+    Lorebook,
+    #[sea_orm(has_one = "super::student_bond::Entity")]
+    StudentBond,
+    #[sea_orm(has_many = "super::student_memory::Entity")]
+    StudentMemory,
+    #[sea_orm(has_one = "super::student_profile::Entity")]
+    StudentProfile,
+    #[sea_orm(has_many = "super::student_prompt_revision::Entity")]
+    StudentPromptRevision,
+}
+
+impl Related<super::anniversary::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Anniversary.def()
+    }
 }
 
 impl Related<super::conversation::Entity> for Entity {
@@ -26,4 +55,40 @@ impl Related<super::conversation::Entity> for Entity {
     }
 }
 
+impl Related<super::lore_chunk::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LoreChunk.def()
+    }
+}
+
+impl Related<super::lorebook::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Lorebook.def()
+    }
+}
+
+impl Related<super::student_bond::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StudentBond.def()
+    }
+}
+
+impl Related<super::student_memory::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StudentMemory.def()
+    }
+}
+
+impl Related<super::student_profile::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StudentProfile.def()
+    }
+}
+
+impl Related<super::student_prompt_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StudentPromptRevision.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
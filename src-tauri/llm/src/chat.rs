@@ -35,7 +35,26 @@ pub async fn chat_with_ds(
     messages: Vec<MessageData>,
     api_key: Option<String>,
 ) -> Result<Message, Box<dyn std::error::Error>> {
-    let model = "deepseek-reasoner";
+    chat_with_ds_model(messages, api_key, REASONER_MODEL).await
+}
+
+/// 角色扮演回复使用的推理模型
+pub const REASONER_MODEL: &str = "deepseek-reasoner";
+
+/// 评分、提取等辅助任务使用的对话模型，响应更快
+pub const CHAT_MODEL: &str = "deepseek-chat";
+
+/// 使用指定模型向 DeepSeek API 发送聊天请求
+///
+/// # 参数
+/// * `messages` - 聊天消息列表
+/// * `api_key` - DeepSeek API Key
+/// * `model` - 模型名称，如 [`REASONER_MODEL`]、[`CHAT_MODEL`]
+pub async fn chat_with_ds_model(
+    messages: Vec<MessageData>,
+    api_key: Option<String>,
+    model: &str,
+) -> Result<Message, Box<dyn std::error::Error>> {
     let base_url = "https://api.deepseek.com/chat/completions";
     let api_key = match api_key {
         Some(key) => key,
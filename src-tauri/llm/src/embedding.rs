@@ -0,0 +1,96 @@
+use reqwest::header::AUTHORIZATION;
+
+use crate::model::{EmbeddingRequest, EmbeddingResponse};
+
+/// 默认的向量化接口，兼容 OpenAI embeddings 格式
+pub const DEFAULT_EMBEDDING_URL: &str = "https://api.siliconflow.cn/v1/embeddings";
+
+/// 默认的向量化模型，对中文效果较好
+pub const DEFAULT_EMBEDDING_MODEL: &str = "BAAI/bge-m3";
+
+/// 向量化接口配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingConfig {
+    pub base_url: String,
+    pub model: String,
+    pub api_key: String,
+}
+
+impl EmbeddingConfig {
+    /// 使用默认接口与模型
+    pub fn new(api_key: String) -> Self {
+        Self {
+            base_url: DEFAULT_EMBEDDING_URL.to_string(),
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            api_key,
+        }
+    }
+}
+
+/// 将文本转换为向量
+///
+/// # 参数
+/// * `inputs` - 需要向量化的文本列表
+/// * `config` - 向量化接口配置
+///
+/// # 返回值
+/// * `Result<Vec<Vec<f32>>, Box<dyn std::error::Error>>` - 与输入顺序一致的向量列表
+///
+/// # 错误
+/// 此函数可能在以下情况返回错误：
+/// * API 请求发送失败
+/// * 响应解析失败或返回的向量数量与输入不一致
+pub async fn create_embeddings(
+    inputs: Vec<String>,
+    config: &EmbeddingConfig,
+) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+    if inputs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let input_count = inputs.len();
+    let request_body = EmbeddingRequest {
+        model: config.model.clone(),
+        input: inputs,
+        encoding_format: "float".to_string(),
+    };
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(&config.base_url)
+        .header(AUTHORIZATION, format!("Bearer {}", config.api_key))
+        .json(&request_body)
+        .send()
+        .await?;
+
+    // 检查响应状态
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await?;
+        return Err(format!("API请求失败: 状态码 {}, 错误信息: {}", status, error_text).into());
+    }
+
+    let response_text = response.text().await?;
+    let embedding: EmbeddingResponse = match serde_json::from_str(&response_text) {
+        Ok(embedding) => embedding,
+        Err(e) => {
+            return Err(format!("解析响应失败: {}. 原始响应: {}", e, response_text).into());
+        }
+    };
+
+    if embedding.data.len() != input_count {
+        return Err(format!(
+            "返回的向量数量不一致: 期望 {}, 实际 {}",
+            input_count,
+            embedding.data.len()
+        )
+        .into());
+    }
+
+    // 按index排序，保证与输入顺序一致
+    let mut data = embedding.data;
+    data.sort_by_key(|d| d.index);
+
+    Ok(data.into_iter().map(|d| d.embedding).collect())
+}
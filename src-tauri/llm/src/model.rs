@@ -37,7 +37,8 @@ pub struct Choice {
 pub struct Message {
     pub role: String,
     pub content: String,
-    #[serde(rename = "reasoning_content")]
+    // 非推理模型的响应中没有该字段
+    #[serde(rename = "reasoning_content", default)]
     pub reasoning_content: String,
 }
 
@@ -50,9 +51,10 @@ pub struct Usage {
     pub completion_tokens: i64,
     #[serde(rename = "total_tokens")]
     pub total_tokens: i64,
-    #[serde(rename = "prompt_tokens_details")]
+    #[serde(rename = "prompt_tokens_details", default)]
     pub prompt_tokens_details: PromptTokensDetails,
-    #[serde(rename = "completion_tokens_details")]
+    // 非推理模型的响应中没有该字段
+    #[serde(rename = "completion_tokens_details", default)]
     pub completion_tokens_details: CompletionTokensDetails,
     #[serde(rename = "prompt_cache_hit_tokens")]
     pub prompt_cache_hit_tokens: i64,
@@ -79,3 +81,23 @@ pub struct MessageData {
     pub role: String,
     pub content: String,
 }
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    pub model: String,
+    pub input: Vec<String>,
+    #[serde(rename = "encoding_format")]
+    pub encoding_format: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    pub data: Vec<EmbeddingData>,
+    pub model: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingData {
+    pub index: usize,
+    pub embedding: Vec<f32>,
+}
@@ -2,6 +2,28 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
 mod m20250310_032013_add_message;
+mod m20250318_091512_add_conversation_last_read_index;
+mod m20250320_140233_add_message_cursor_index;
+mod m20250324_102647_add_student_custom_fields;
+mod m20250327_163408_add_prompt_template;
+mod m20250331_201155_remove_stored_system_prompt;
+mod m20250403_110520_add_student_prompt_revision;
+mod m20250407_150942_add_student_profile;
+mod m20250411_093017_add_app_meta;
+mod m20250415_104836_add_avatar_variants;
+mod m20250418_142305_add_persona;
+mod m20250422_091736_add_student_bond;
+mod m20250425_160412_add_student_memory;
+mod m20250429_203158_add_embedding;
+mod m20250502_141827_add_lore_chunk;
+mod m20250506_093412_add_lorebook;
+mod m20250509_201544_add_message_proactive;
+mod m20250513_172209_add_anniversary;
+mod m20250516_103527_add_message_turn_id;
+mod m20250520_151204_add_message_delivery;
+mod m20250524_113946_add_conversation_title_locked;
+mod m20250527_142018_add_prompt_revision_template;
+mod m20250530_103214_add_student_profile_voice_actor;
 
 pub struct Migrator;
 
@@ -11,6 +33,28 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20250310_032013_add_message::Migration),
+            Box::new(m20250318_091512_add_conversation_last_read_index::Migration),
+            Box::new(m20250320_140233_add_message_cursor_index::Migration),
+            Box::new(m20250324_102647_add_student_custom_fields::Migration),
+            Box::new(m20250327_163408_add_prompt_template::Migration),
+            Box::new(m20250331_201155_remove_stored_system_prompt::Migration),
+            Box::new(m20250403_110520_add_student_prompt_revision::Migration),
+            Box::new(m20250407_150942_add_student_profile::Migration),
+            Box::new(m20250411_093017_add_app_meta::Migration),
+            Box::new(m20250415_104836_add_avatar_variants::Migration),
+            Box::new(m20250418_142305_add_persona::Migration),
+            Box::new(m20250422_091736_add_student_bond::Migration),
+            Box::new(m20250425_160412_add_student_memory::Migration),
+            Box::new(m20250429_203158_add_embedding::Migration),
+            Box::new(m20250502_141827_add_lore_chunk::Migration),
+            Box::new(m20250506_093412_add_lorebook::Migration),
+            Box::new(m20250509_201544_add_message_proactive::Migration),
+            Box::new(m20250513_172209_add_anniversary::Migration),
+            Box::new(m20250516_103527_add_message_turn_id::Migration),
+            Box::new(m20250520_151204_add_message_delivery::Migration),
+            Box::new(m20250524_113946_add_conversation_title_locked::Migration),
+            Box::new(m20250527_142018_add_prompt_revision_template::Migration),
+            Box::new(m20250530_103214_add_student_profile_voice_actor::Migration),
         ]
     }
 }
@@ -0,0 +1,49 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 记录用户在对话中已读到的消息索引，用于计算未读数
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .add_column(integer(Conversation::LastReadIndex).default(-1))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 已有的对话视为已读到最后一条消息
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE conversation SET last_read_index = COALESCE(\
+             (SELECT MAX(m.\"index\") FROM message m \
+             WHERE m.conversation_id = conversation.id), -1)",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .drop_column(Conversation::LastReadIndex)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Conversation {
+    Table,
+    LastReadIndex, // 已读到的消息索引，-1 表示从未读过
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 用 (conversation_id, index) 复合索引支撑游标分页，替代原来的单列索引
+        manager
+            .create_index(
+                Index::create()
+                    .table(Message::Table)
+                    .name("idx_message_conversation_id_index")
+                    .col(Message::ConversationId)
+                    .col(Message::Index)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Message::Table)
+                    .name("idx_message_conversation_id")
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_index(
+                Index::create()
+                    .table(Message::Table)
+                    .name("idx_message_conversation_id")
+                    .col(Message::ConversationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Message::Table)
+                    .name("idx_message_conversation_id_index")
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    ConversationId,
+    Index,
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 区分用户自定义角色与数据集中的官方学生
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .add_column(boolean(Student::IsCustom).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 角色的附加信息，JSON 格式
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .add_column(text(Student::Metadata).default("{}"))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .drop_column(Student::Metadata)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .drop_column(Student::IsCustom)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    IsCustom, // 是否为用户自定义角色
+    Metadata, // 附加信息（JSON）
+}
@@ -0,0 +1,100 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建prompt模板表
+        manager
+            .create_table(
+                Table::create()
+                    .table(PromptTemplate::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PromptTemplate::Id))
+                    .col(string_uniq(PromptTemplate::Name))
+                    .col(text(PromptTemplate::Content))
+                    .col(
+                        timestamp_with_time_zone(PromptTemplate::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        timestamp_with_time_zone(PromptTemplate::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 学生的背景资料，原先在导入时直接拼进prompt
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .add_column(text(Student::Content).default(""))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 学生使用的模板，为空时使用默认模板
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .add_column(integer_null(Student::PromptTemplateId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 官方学生的prompt是由旧模板生成的，清空后改为请求时渲染
+        let db = manager.get_connection();
+        db.execute_unprepared("UPDATE student SET prompt = '' WHERE is_custom = FALSE")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .drop_column(Student::PromptTemplateId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .drop_column(Student::Content)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PromptTemplate::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum PromptTemplate {
+    Table,
+    Id,
+    Name,      // 模板名称
+    Content,   // 模板内容
+    CreatedAt, // 创建时间
+    UpdatedAt, // 更新时间
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Content,          // 背景资料
+    PromptTemplateId, // 使用的模板
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 对话级别的补充设定，组装system prompt时追加
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .add_column(text_null(Conversation::PromptOverride))
+                    .to_owned(),
+            )
+            .await?;
+
+        // system prompt改为每次请求时组装，删除旧版本保存在对话中的system消息
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Message::Table)
+                    .and_where(Expr::col(Message::Role).eq("system"))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已删除的system消息无需恢复，下次请求时会重新组装
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .drop_column(Conversation::PromptOverride)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Conversation {
+    Table,
+    PromptOverride, // 对话级别的补充设定
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Role,
+}
@@ -0,0 +1,107 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建学生prompt历史版本表
+        manager
+            .create_table(
+                Table::create()
+                    .table(StudentPromptRevision::Table)
+                    .if_not_exists()
+                    .col(pk_auto(StudentPromptRevision::Id))
+                    .col(integer(StudentPromptRevision::StudentId))
+                    .col(text(StudentPromptRevision::Prompt))
+                    .col(string_null(StudentPromptRevision::Note))
+                    .col(
+                        timestamp_with_time_zone(StudentPromptRevision::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                StudentPromptRevision::Table,
+                                StudentPromptRevision::StudentId,
+                            )
+                            .to(Student::Table, Student::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(StudentPromptRevision::Table)
+                    .name("idx_student_prompt_revision_student_id")
+                    .col(StudentPromptRevision::StudentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // 记录助手消息生成时使用的prompt版本
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(integer_null(Message::PromptRevisionId))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::PromptRevisionId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(StudentPromptRevision::Table)
+                    .name("idx_student_prompt_revision_student_id")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(StudentPromptRevision::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum StudentPromptRevision {
+    Table,
+    Id,
+    StudentId, // 所属学生
+    Prompt,    // 该版本的prompt内容
+    Note,      // 修改备注
+    CreatedAt, // 创建时间
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    PromptRevisionId, // 生成该消息时使用的prompt版本
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建学生资料表，与学生一对一
+        manager
+            .create_table(
+                Table::create()
+                    .table(StudentProfile::Table)
+                    .if_not_exists()
+                    .col(integer(StudentProfile::StudentId).primary_key())
+                    .col(string_null(StudentProfile::FullName))
+                    .col(string_null(StudentProfile::School))
+                    .col(string_null(StudentProfile::Club))
+                    .col(string_null(StudentProfile::Grade))
+                    .col(string_null(StudentProfile::Age))
+                    .col(string_null(StudentProfile::Birthday))
+                    .col(integer_null(StudentProfile::BirthdayMonth))
+                    .col(integer_null(StudentProfile::BirthdayDay))
+                    .col(string_null(StudentProfile::Height))
+                    .col(string_null(StudentProfile::Hobbies))
+                    .col(text_null(StudentProfile::Bio))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(StudentProfile::Table, StudentProfile::StudentId)
+                            .to(Student::Table, Student::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StudentProfile::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum StudentProfile {
+    Table,
+    StudentId,     // 所属学生
+    FullName,      // 全名
+    School,        // 所属学校
+    Club,          // 所属社团
+    Grade,         // 年级
+    Age,           // 年龄
+    Birthday,      // 生日原文
+    BirthdayMonth, // 生日月份
+    BirthdayDay,   // 生日日期
+    Height,        // 身高
+    Hobbies,       // 爱好
+    Bio,           // 个人简介
+}
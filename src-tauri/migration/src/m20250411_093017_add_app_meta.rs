@@ -0,0 +1,38 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建应用元数据表，用于记录数据集版本等键值信息
+        manager
+            .create_table(
+                Table::create()
+                    .table(AppMeta::Table)
+                    .if_not_exists()
+                    .col(string(AppMeta::Key).primary_key())
+                    .col(text(AppMeta::Value))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AppMeta::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum AppMeta {
+    Table,
+    Key,   // 键
+    Value, // 值
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 头像对应的服装名称，如泳装、正月，JSON对象，键为头像地址
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .add_column(text(Student::AvatarLabels).default("{}"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 对话中选择的头像，为空时使用第一个头像
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .add_column(string_null(Conversation::Avatar))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .drop_column(Conversation::Avatar)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .drop_column(Student::AvatarLabels)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    AvatarLabels, // 头像对应的服装名称
+}
+
+#[derive(DeriveIden)]
+enum Conversation {
+    Table,
+    Avatar, // 选择的头像
+}
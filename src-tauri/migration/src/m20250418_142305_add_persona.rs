@@ -0,0 +1,81 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建用户人设表，同一时间只有一个人设处于启用状态
+        manager
+            .create_table(
+                Table::create()
+                    .table(Persona::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Persona::Id))
+                    .col(string(Persona::Name))
+                    .col(string(Persona::Address))
+                    .col(text_null(Persona::Bio))
+                    .col(text_null(Persona::Preferences))
+                    .col(boolean(Persona::IsActive).default(false))
+                    .col(
+                        timestamp_with_time_zone(Persona::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        timestamp_with_time_zone(Persona::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 对话使用的人设，为空时使用启用的人设
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .add_column(integer_null(Conversation::PersonaId))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .drop_column(Conversation::PersonaId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Persona::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Persona {
+    Table,
+    Id,          // 人设ID
+    Name,        // 显示名称
+    Address,     // 学生对用户的称呼
+    Bio,         // 简介
+    Preferences, // 偏好
+    IsActive,    // 是否启用
+    CreatedAt,   // 创建时间
+    UpdatedAt,   // 更新时间
+}
+
+#[derive(DeriveIden)]
+enum Conversation {
+    Table,
+    PersonaId, // 对话使用的人设
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建学生羁绊表，记录与每个学生的好感度
+        manager
+            .create_table(
+                Table::create()
+                    .table(StudentBond::Table)
+                    .if_not_exists()
+                    .col(integer(StudentBond::StudentId).primary_key())
+                    .col(integer(StudentBond::Points).default(0))
+                    .col(integer(StudentBond::Level).default(1))
+                    .col(
+                        timestamp_with_time_zone(StudentBond::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(StudentBond::Table, StudentBond::StudentId)
+                            .to(Student::Table, Student::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StudentBond::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum StudentBond {
+    Table,
+    StudentId, // 所属学生
+    Points,    // 累计羁绊点数
+    Level,     // 羁绊等级
+    UpdatedAt, // 更新时间
+}
@@ -0,0 +1,77 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建学生记忆表，保存从对话中提取的长期记忆
+        manager
+            .create_table(
+                Table::create()
+                    .table(StudentMemory::Table)
+                    .if_not_exists()
+                    .col(pk_auto(StudentMemory::Id))
+                    .col(integer(StudentMemory::StudentId))
+                    .col(text(StudentMemory::Content))
+                    .col(integer(StudentMemory::Importance).default(1))
+                    .col(
+                        timestamp_with_time_zone(StudentMemory::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        timestamp_with_time_zone(StudentMemory::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(timestamp_with_time_zone_null(StudentMemory::LastUsedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(StudentMemory::Table, StudentMemory::StudentId)
+                            .to(Student::Table, Student::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_student_memory_student_id")
+                    .table(StudentMemory::Table)
+                    .col(StudentMemory::StudentId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StudentMemory::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum StudentMemory {
+    Table,
+    Id,         // 记忆ID
+    StudentId,  // 所属学生
+    Content,    // 记忆内容
+    Importance, // 重要程度，1到5
+    CreatedAt,  // 创建时间
+    UpdatedAt,  // 更新时间
+    LastUsedAt, // 上次注入prompt的时间
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建向量表，保存消息与记忆的向量，检索时在内存中计算余弦相似度
+        manager
+            .create_table(
+                Table::create()
+                    .table(Embedding::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Embedding::Id))
+                    .col(string(Embedding::SourceType))
+                    .col(integer(Embedding::SourceId))
+                    .col(string(Embedding::Model))
+                    .col(blob(Embedding::Vector))
+                    .col(
+                        timestamp_with_time_zone(Embedding::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 每条消息或记忆只保存一个向量
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_embedding_source")
+                    .table(Embedding::Table)
+                    .col(Embedding::SourceType)
+                    .col(Embedding::SourceId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Embedding::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Embedding {
+    Table,
+    Id,         // 向量ID
+    SourceType, // 来源类型，message或memory
+    SourceId,   // 来源ID
+    Model,      // 生成向量的模型
+    Vector,     // 向量，小端序f32
+    CreatedAt,  // 创建时间
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建背景资料片段表，保存切分后的学生资料及其检索词元
+        manager
+            .create_table(
+                Table::create()
+                    .table(LoreChunk::Table)
+                    .if_not_exists()
+                    .col(pk_auto(LoreChunk::Id))
+                    .col(integer(LoreChunk::StudentId))
+                    .col(integer(LoreChunk::Position))
+                    .col(string(LoreChunk::Heading).default(""))
+                    .col(text(LoreChunk::Content))
+                    .col(text(LoreChunk::Terms).default("{}"))
+                    .col(integer(LoreChunk::Length).default(0))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(LoreChunk::Table, LoreChunk::StudentId)
+                            .to(Student::Table, Student::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_lore_chunk_student_id")
+                    .table(LoreChunk::Table)
+                    .col(LoreChunk::StudentId)
+                    .col(LoreChunk::Position)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoreChunk::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum LoreChunk {
+    Table,
+    Id,        // 片段ID
+    StudentId, // 所属学生
+    Position,  // 在资料中的顺序
+    Heading,   // 所在章节的标题
+    Content,   // 片段内容
+    Terms,     // 词元及出现次数，JSON对象
+    Length,    // 词元总数
+}
@@ -0,0 +1,139 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建世界书表，作用范围为全局、指定学校或指定学生
+        manager
+            .create_table(
+                Table::create()
+                    .table(Lorebook::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Lorebook::Id))
+                    .col(string(Lorebook::Name))
+                    .col(string(Lorebook::Scope).default("global"))
+                    .col(string_null(Lorebook::School))
+                    .col(integer_null(Lorebook::StudentId))
+                    .col(integer(Lorebook::ScanDepth).default(4))
+                    .col(integer(Lorebook::TokenBudget).default(1024))
+                    .col(boolean(Lorebook::Enabled).default(true))
+                    .col(
+                        timestamp_with_time_zone(Lorebook::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        timestamp_with_time_zone(Lorebook::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Lorebook::Table, Lorebook::StudentId)
+                            .to(Student::Table, Student::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // 创建世界书词条表
+        manager
+            .create_table(
+                Table::create()
+                    .table(LorebookEntry::Table)
+                    .if_not_exists()
+                    .col(pk_auto(LorebookEntry::Id))
+                    .col(integer(LorebookEntry::LorebookId))
+                    .col(string(LorebookEntry::Title).default(""))
+                    .col(text(LorebookEntry::Keywords).default("[]"))
+                    .col(boolean(LorebookEntry::UseRegex).default(false))
+                    .col(text(LorebookEntry::Content))
+                    .col(string(LorebookEntry::Position).default("after"))
+                    .col(integer(LorebookEntry::Priority).default(0))
+                    .col(boolean(LorebookEntry::Constant).default(false))
+                    .col(boolean(LorebookEntry::Enabled).default(true))
+                    .col(
+                        timestamp_with_time_zone(LorebookEntry::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        timestamp_with_time_zone(LorebookEntry::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(LorebookEntry::Table, LorebookEntry::LorebookId)
+                            .to(Lorebook::Table, Lorebook::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_lorebook_entry_lorebook_id")
+                    .table(LorebookEntry::Table)
+                    .col(LorebookEntry::LorebookId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LorebookEntry::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Lorebook::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Lorebook {
+    Table,
+    Id,          // 世界书ID
+    Name,        // 名称
+    Scope,       // 作用范围：global、school或student
+    School,      // 作用的学校，scope为school时有效
+    StudentId,   // 作用的学生，scope为student时有效
+    ScanDepth,   // 扫描最近消息的数量
+    TokenBudget, // 注入词条的token上限
+    Enabled,     // 是否启用
+    CreatedAt,   // 创建时间
+    UpdatedAt,   // 更新时间
+}
+
+#[derive(DeriveIden)]
+enum LorebookEntry {
+    Table,
+    Id,         // 词条ID
+    LorebookId, // 所属世界书
+    Title,      // 词条标题，仅用于管理
+    Keywords,   // 触发关键词，JSON数组
+    UseRegex,   // 关键词是否为正则表达式
+    Content,    // 注入的内容
+    Position,   // 插入位置：before或after学生设定
+    Priority,   // 优先级，超出预算时优先保留高优先级的词条
+    Constant,   // 是否无需触发始终注入
+    Enabled,    // 是否启用
+    CreatedAt,  // 创建时间
+    UpdatedAt,  // 更新时间
+}
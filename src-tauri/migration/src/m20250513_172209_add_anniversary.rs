@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 创建纪念日表，保存用户自定义的每年重复的日子
+        manager
+            .create_table(
+                Table::create()
+                    .table(Anniversary::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Anniversary::Id))
+                    .col(integer_null(Anniversary::StudentId))
+                    .col(string(Anniversary::Title))
+                    .col(integer(Anniversary::Month))
+                    .col(integer(Anniversary::Day))
+                    .col(integer_null(Anniversary::Year))
+                    .col(text_null(Anniversary::Prompt))
+                    .col(
+                        timestamp_with_time_zone(Anniversary::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        timestamp_with_time_zone(Anniversary::UpdatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Anniversary::Table, Anniversary::StudentId)
+                            .to(Student::Table, Student::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Anniversary::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Anniversary {
+    Table,
+    Id,        // 纪念日ID
+    StudentId, // 相关的学生，为空时与所有学生相关
+    Title,     // 名称
+    Month,     // 月
+    Day,       // 日
+    Year,      // 起始年份，用于计算周年数
+    Prompt,    // 当天注入prompt的内容
+    CreatedAt, // 创建时间
+    UpdatedAt, // 更新时间
+}
@@ -0,0 +1,93 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 记录用户消息被学生读到的时间与学生消息送达的时间
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(timestamp_with_time_zone_null(Message::ReadAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(timestamp_with_time_zone_null(Message::DeliveredAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 学生的打字速度，为空时使用默认速度
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .add_column(integer_null(Student::TypingSpeed))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 已有的消息视为已读与已送达
+        let db = manager.get_connection();
+        db.execute_unprepared("UPDATE message SET read_at = created_at WHERE role = 'user'")
+            .await?;
+        db.execute_unprepared(
+            "UPDATE message SET delivered_at = created_at WHERE role = 'assistant'",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Student::Table)
+                    .drop_column(Student::TypingSpeed)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::DeliveredAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::ReadAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    ReadAt,      // 用户消息被读到的时间
+    DeliveredAt, // 学生消息送达的时间
+}
+
+#[derive(DeriveIden)]
+enum Student {
+    Table,
+    TypingSpeed, // 打字速度（字/分钟）
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 用户手动设置的标题不再被自动生成的标题覆盖
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .add_column(boolean(Conversation::TitleLocked).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 已有的对话中与学生名不同的标题视为用户手动设置
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE conversation SET title_locked = 1 \
+             WHERE title <> '' AND title <> student_name",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Conversation::Table)
+                    .drop_column(Conversation::TitleLocked)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Conversation {
+    Table,
+    TitleLocked, // 标题是否由用户锁定
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 官方学生的行为主要来自模板，版本中同时记录当时使用的模板
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StudentPromptRevision::Table)
+                    .add_column(integer_null(StudentPromptRevision::PromptTemplateId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StudentPromptRevision::Table)
+                    .add_column(text_null(StudentPromptRevision::Template))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StudentPromptRevision::Table)
+                    .drop_column(StudentPromptRevision::Template)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StudentPromptRevision::Table)
+                    .drop_column(StudentPromptRevision::PromptTemplateId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum StudentPromptRevision {
+    Table,
+    PromptTemplateId, // 该版本使用的模板，模板删除后保留原ID
+    Template,         // 该版本使用的模板内容
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 学生资料中的声优
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StudentProfile::Table)
+                    .add_column(string_null(StudentProfile::VoiceActor))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StudentProfile::Table)
+                    .drop_column(StudentProfile::VoiceActor)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum StudentProfile {
+    Table,
+    VoiceActor, // 声优
+}
@@ -0,0 +1,96 @@
+use entity::student;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// 自定义角色头像在应用数据目录下的存放位置
+pub const CUSTOM_AVATAR_DIR: &str = "avatars/custom";
+
+// 将用户选择的头像文件复制到本地头像目录，返回保存后的路径
+//
+// 已经位于头像目录中的文件（例如更新时保留的旧头像）直接沿用。
+pub fn import_custom_avatars(dir: &Path, sources: &[String]) -> std::io::Result<Vec<String>> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut avatars = Vec::with_capacity(sources.len());
+    for source in sources {
+        if resolve_custom_avatar(dir, source).is_some() {
+            avatars.push(source.clone());
+            continue;
+        }
+
+        let source_path = PathBuf::from(source);
+
+        let extension = source_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("png");
+        let target = dir.join(format!("{}.{}", uuid::Uuid::new_v4(), extension));
+        std::fs::copy(&source_path, &target)?;
+
+        avatars.push(target.to_string_lossy().to_string());
+    }
+
+    Ok(avatars)
+}
+
+// 删除不再被引用的自定义头像，只处理头像目录中的文件
+pub fn remove_custom_avatars(dir: &Path, avatars: &[String]) {
+    for avatar in avatars {
+        let Some(path) = resolve_custom_avatar(dir, avatar) else {
+            continue;
+        };
+        if let Err(e) = std::fs::remove_file(&path) {
+            println!("删除头像 {} 失败: {}", path.display(), e);
+        }
+    }
+}
+
+// 解析头像目录中的文件，返回规范化后的路径
+//
+// 按路径组件比较无法识别..，因此两边都先规范化；不在头像目录中或文件不存在时返回None
+pub fn resolve_custom_avatar(dir: &Path, avatar: &str) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    let path = Path::new(avatar).canonicalize().ok()?;
+
+    (path.starts_with(&dir) && path.is_file()).then_some(path)
+}
+
+// 解析student.avatars中保存的JSON数组
+pub fn parse_avatars(avatars: &str) -> Vec<String> {
+    serde_json::from_str(avatars).unwrap_or_default()
+}
+
+// 学生的一个头像，label为服装名称，如泳装、正月
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvatarVariant {
+    pub index: usize,
+    pub url: String,
+    pub label: Option<String>,
+}
+
+// 列出学生的所有头像
+pub fn avatar_variants(student: &student::Model) -> Vec<AvatarVariant> {
+    let labels: HashMap<String, String> =
+        serde_json::from_str(&student.avatar_labels).unwrap_or_default();
+
+    parse_avatars(&student.avatars)
+        .into_iter()
+        .enumerate()
+        .map(|(index, url)| AvatarVariant {
+            index,
+            label: labels.get(&url).cloned(),
+            url,
+        })
+        .collect()
+}
+
+// 对话中使用的头像，未选择或选择的头像已不存在时使用第一个
+pub fn selected_variant(student: &student::Model, selected: Option<&str>) -> Option<AvatarVariant> {
+    let mut variants = avatar_variants(student);
+    let index = selected
+        .and_then(|url| variants.iter().position(|v| v.url == url))
+        .unwrap_or(0);
+
+    (index < variants.len()).then(|| variants.swap_remove(index))
+}
@@ -0,0 +1,328 @@
+use crate::avatar;
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// 头像缓存在应用数据目录下的存放位置
+pub const AVATAR_CACHE_DIR: &str = "avatars/cache";
+
+// 提供头像的自定义协议，前端通过 convertFileSrc(src, "avatar") 访问
+pub const AVATAR_SCHEME: &str = "avatar";
+
+// 缓存索引文件，记录每个远程地址对应的本地文件与ETag
+const INDEX_FILE: &str = "index.json";
+
+// 缓存超过该时长（秒）后，访问时在后台重新验证
+const REVALIDATE_AFTER_SECS: i64 = 24 * 60 * 60;
+
+// 预取时同时进行的下载数量
+const PREFETCH_CONCURRENCY: usize = 8;
+
+// 头像无法获取时使用的内置占位图
+const PLACEHOLDER: &[u8] = include_bytes!("../assets/avatar_placeholder.svg");
+const PLACEHOLDER_CONTENT_TYPE: &str = "image/svg+xml";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    file: String,
+    etag: Option<String>,
+    content_type: Option<String>,
+    // 上次与服务器确认的时间戳
+    checked_at: i64,
+}
+
+pub struct AvatarImage {
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+}
+
+impl AvatarImage {
+    pub fn placeholder() -> Self {
+        Self {
+            bytes: PLACEHOLDER.to_vec(),
+            content_type: PLACEHOLDER_CONTENT_TYPE.to_string(),
+        }
+    }
+}
+
+// 预取结果，供前端展示
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PrefetchReport {
+    pub total: usize,
+    pub downloaded: usize,
+    pub not_modified: usize,
+    pub failed: Vec<String>,
+}
+
+enum FetchOutcome {
+    Downloaded,
+    NotModified,
+}
+
+// 远程头像的本地缓存
+//
+// 图片下载到缓存目录，再次请求时携带ETag重新验证；
+// 离线或下载失败时使用已缓存的图片，没有缓存则返回占位图。
+#[derive(Clone)]
+pub struct AvatarCache {
+    dir: PathBuf,
+    custom_dir: PathBuf,
+    client: reqwest::Client,
+    index: Arc<Mutex<HashMap<String, CacheEntry>>>,
+}
+
+impl AvatarCache {
+    pub fn new(dir: PathBuf, custom_dir: PathBuf) -> Self {
+        let index = std::fs::read(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+
+        Self {
+            dir,
+            custom_dir,
+            client: reqwest::Client::new(),
+            index: Arc::new(Mutex::new(index)),
+        }
+    }
+
+    // 读取头像，src可以是远程地址或自定义头像的本地路径
+    pub async fn load(&self, src: &str) -> AvatarImage {
+        if !is_remote(src) {
+            return self.load_local(src);
+        }
+
+        if let Some(image) = self.load_cached(src).await {
+            return image;
+        }
+
+        if let Err(e) = self.fetch(src).await {
+            println!("下载头像 {} 失败: {}", src, e);
+        }
+        self.load_cached(src)
+            .await
+            .unwrap_or_else(AvatarImage::placeholder)
+    }
+
+    // 下载或重新验证所有头像
+    pub async fn prefetch(&self, urls: Vec<String>) -> PrefetchReport {
+        let mut report = PrefetchReport {
+            total: urls.len(),
+            ..Default::default()
+        };
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for url in urls {
+            if tasks.len() >= PREFETCH_CONCURRENCY {
+                if let Some(result) = tasks.join_next().await {
+                    record_prefetch(&mut report, result);
+                }
+            }
+            let cache = self.clone();
+            tasks.spawn(async move {
+                let outcome = cache.fetch(&url).await;
+                (url, outcome)
+            });
+        }
+        while let Some(result) = tasks.join_next().await {
+            record_prefetch(&mut report, result);
+        }
+
+        println!(
+            "头像预取完成: 下载 {} 个，未变化 {} 个，失败 {} 个",
+            report.downloaded,
+            report.not_modified,
+            report.failed.len()
+        );
+        report
+    }
+
+    // 只允许读取自定义头像目录中的文件
+    fn load_local(&self, src: &str) -> AvatarImage {
+        let Some(path) = avatar::resolve_custom_avatar(&self.custom_dir, src) else {
+            return AvatarImage::placeholder();
+        };
+
+        match std::fs::read(&path) {
+            Ok(bytes) => AvatarImage {
+                bytes,
+                content_type: content_type_for(&path).to_string(),
+            },
+            Err(_) => AvatarImage::placeholder(),
+        }
+    }
+
+    async fn load_cached(&self, url: &str) -> Option<AvatarImage> {
+        let entry = self.index.lock().await.get(url).cloned()?;
+        let path = self.dir.join(&entry.file);
+        let bytes = tokio::fs::read(&path).await.ok()?;
+
+        // 缓存过期时后台重新验证，本次仍返回已缓存的图片
+        if now() - entry.checked_at > REVALIDATE_AFTER_SECS {
+            let cache = self.clone();
+            let url = url.to_string();
+            tokio::spawn(async move {
+                if let Err(e) = cache.fetch(&url).await {
+                    println!("重新验证头像 {} 失败: {}", url, e);
+                }
+            });
+        }
+
+        Some(AvatarImage {
+            bytes,
+            content_type: entry
+                .content_type
+                .unwrap_or_else(|| content_type_for(&path).to_string()),
+        })
+    }
+
+    async fn fetch(&self, url: &str) -> Result<FetchOutcome, String> {
+        let entry = self.index.lock().await.get(url).cloned();
+        // 本地文件丢失时不再携带ETag，重新下载
+        let entry = entry.filter(|e| self.dir.join(&e.file).exists());
+
+        let mut request = self.client.get(url);
+        if let Some(etag) = entry.as_ref().and_then(|e| e.etag.as_ref()) {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut entry) = entry {
+                entry.checked_at = now();
+                self.save_entry(url, entry).await?;
+            }
+            return Ok(FetchOutcome::NotModified);
+        }
+        if !response.status().is_success() {
+            return Err(format!("服务器返回 {}", response.status()));
+        }
+
+        let headers = response.headers();
+        let etag = headers
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+
+        let file = entry
+            .map(|e| e.file)
+            .unwrap_or_else(|| cache_file_name(url));
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::write(self.dir.join(&file), &bytes)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        self.save_entry(
+            url,
+            CacheEntry {
+                file,
+                etag,
+                content_type,
+                checked_at: now(),
+            },
+        )
+        .await?;
+        Ok(FetchOutcome::Downloaded)
+    }
+
+    async fn save_entry(&self, url: &str, entry: CacheEntry) -> Result<(), String> {
+        let mut index = self.index.lock().await;
+        index.insert(url.to_string(), entry);
+
+        let json = serde_json::to_vec(&*index).map_err(|e| e.to_string())?;
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::write(self.dir.join(INDEX_FILE), json)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+// 从自定义协议的请求路径中取出头像地址
+//
+// convertFileSrc 会对地址做百分号编码，如 /https%3A%2F%2F...%2F10000.webp
+pub fn source_from_path(path: &str) -> String {
+    let bytes = path.trim_start_matches('/').as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+pub fn is_remote(src: &str) -> bool {
+    src.starts_with("http://") || src.starts_with("https://")
+}
+
+fn record_prefetch(
+    report: &mut PrefetchReport,
+    result: Result<(String, Result<FetchOutcome, String>), tokio::task::JoinError>,
+) {
+    match result {
+        Ok((_, Ok(FetchOutcome::Downloaded))) => report.downloaded += 1,
+        Ok((_, Ok(FetchOutcome::NotModified))) => report.not_modified += 1,
+        Ok((url, Err(e))) => {
+            println!("预取头像 {} 失败: {}", url, e);
+            report.failed.push(url);
+        }
+        Err(e) => println!("预取头像任务异常: {}", e),
+    }
+}
+
+fn cache_file_name(url: &str) -> String {
+    let extension = url
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext)
+        .filter(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("img");
+    format!("{}.{}", uuid::Uuid::new_v4(), extension)
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
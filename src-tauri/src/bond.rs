@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+// 羁绊等级上限
+pub const MAX_LEVEL: i32 = 20;
+
+// 未启用LLM评分时，每轮对话获得的羁绊点数
+pub const POINTS_PER_EXCHANGE: i32 = 2;
+
+// LLM评分时单轮对话最多获得的羁绊点数
+pub const MAX_SCORED_POINTS: i32 = 5;
+
+// 羁绊阶段，决定学生对用户的语气
+struct Stage {
+    min_level: i32,
+    name: &'static str,
+    guidance: &'static str,
+}
+
+const STAGES: [Stage; 5] = [
+    Stage {
+        min_level: 1,
+        name: "初识",
+        guidance: "你们刚认识不久，说话礼貌但略带距离感，不会主动谈论私事。",
+    },
+    Stage {
+        min_level: 3,
+        name: "熟悉",
+        guidance: "你们已经比较熟悉，语气轻松自然，偶尔会开玩笑或分享日常小事。",
+    },
+    Stage {
+        min_level: 6,
+        name: "信赖",
+        guidance: "你很信赖对方，愿意倾诉烦恼，会主动关心对方的近况。",
+    },
+    Stage {
+        min_level: 10,
+        name: "亲密",
+        guidance: "你们关系亲密，说话坦率随意，会撒娇或表达想念，在意对方的看法。",
+    },
+    Stage {
+        min_level: 15,
+        name: "羁绊",
+        guidance: "对方是你最重要的人之一，你毫无保留地信任对方，言语中自然流露出深厚的感情。",
+    },
+];
+
+// 羁绊状态，供前端展示
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BondStatus {
+    pub student_id: i32,
+    pub points: i32,
+    pub level: i32,
+    pub stage: String,
+    // 当前等级的起始点数
+    pub level_points: i32,
+    // 升到下一级所需的累计点数，已满级时为空
+    pub next_level_points: Option<i32>,
+}
+
+impl BondStatus {
+    pub fn new(student_id: i32, points: i32) -> Self {
+        let level = level_for_points(points);
+
+        Self {
+            student_id,
+            points,
+            level,
+            stage: stage(level).name.to_string(),
+            level_points: level_threshold(level),
+            next_level_points: (level < MAX_LEVEL).then(|| level_threshold(level + 1)),
+        }
+    }
+}
+
+// 达到指定等级所需的累计点数，每升一级所需点数递增
+pub fn level_threshold(level: i32) -> i32 {
+    let n = level.clamp(1, MAX_LEVEL) - 1;
+    5 * n * (n + 1)
+}
+
+pub fn level_for_points(points: i32) -> i32 {
+    (1..=MAX_LEVEL)
+        .rev()
+        .find(|level| points >= level_threshold(*level))
+        .unwrap_or(1)
+}
+
+// 当前等级对应的语气指引
+pub fn tone_guidance(level: i32) -> String {
+    let stage = stage(level);
+    format!(
+        "当前羁绊等级：{}（{}）\n{}",
+        level, stage.name, stage.guidance
+    )
+}
+
+fn stage(level: i32) -> &'static Stage {
+    STAGES
+        .iter()
+        .rev()
+        .find(|stage| level >= stage.min_level)
+        .unwrap_or(&STAGES[0])
+}
+
+// 让LLM为一轮对话评分的提示词
+pub fn scoring_prompt(student_name: &str, user_message: &str, reply: &str) -> String {
+    format!(
+        "以下是用户与{name}的一轮对话。请评估这轮对话让{name}对用户的好感提升了多少，\
+只输出0到{max}之间的一个整数，不要输出其他内容。\n\n用户：{user}\n{name}：{reply}",
+        name = student_name,
+        max = MAX_SCORED_POINTS,
+        user = user_message,
+        reply = reply
+    )
+}
+
+// 从LLM的回复中解析评分
+pub fn parse_score(text: &str) -> Option<i32> {
+    let digits: String = text
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits
+        .parse::<i32>()
+        .ok()
+        .map(|score| score.clamp(0, MAX_SCORED_POINTS))
+}
@@ -0,0 +1,91 @@
+// 要求模型在消息之间使用的分隔符
+pub const DELIMITER: &str = "||";
+
+// 一次回复最多拆分出的消息数量，超出的部分合并到最后一条
+pub const MAX_BUBBLES: usize = 5;
+
+// 拆分回复的方式，对应settings.json中reply_bubbles的值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitMode {
+    // off，不拆分
+    Off,
+    // paragraph，按空行分段
+    Paragraph,
+    // delimiter，按分隔符拆分，模型没有使用分隔符时按空行分段
+    #[default]
+    Delimiter,
+}
+
+impl SplitMode {
+    // 缺失或无法识别时使用分隔符
+    pub fn from_setting(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            Some("off") | Some("false") => SplitMode::Off,
+            Some("paragraph") => SplitMode::Paragraph,
+            _ => SplitMode::Delimiter,
+        }
+    }
+}
+
+// 要求模型用分隔符拆分回复的指令
+pub fn format_instruction() -> String {
+    format!(
+        "像在MomoTalk上聊天一样，把回复拆成几条简短的消息（通常1到3条），\
+每条消息之间用单独一行的「{}」分隔，不要使用其他标记。",
+        DELIMITER
+    )
+}
+
+// 将回复拆分为多条消息，去掉空白的部分，至少返回一条
+pub fn split_reply(content: &str, mode: SplitMode) -> Vec<String> {
+    let content = content.trim();
+    let pieces = match mode {
+        SplitMode::Off => vec![content.to_string()],
+        SplitMode::Delimiter if content.contains(DELIMITER) => {
+            content.split(DELIMITER).map(str::to_string).collect()
+        }
+        SplitMode::Delimiter | SplitMode::Paragraph => paragraphs(content),
+    };
+
+    let mut bubbles: Vec<String> = pieces
+        .iter()
+        .map(|piece| piece.trim().to_string())
+        .filter(|piece| !piece.is_empty())
+        .collect();
+
+    if bubbles.len() > MAX_BUBBLES {
+        let rest = bubbles.split_off(MAX_BUBBLES - 1).join("\n\n");
+        bubbles.push(rest);
+    }
+    if bubbles.is_empty() {
+        bubbles.push(content.to_string());
+    }
+
+    bubbles
+}
+
+// 按空行分段，代码块内的空行不分段
+fn paragraphs(content: &str) -> Vec<String> {
+    let mut paragraphs = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut in_code = false;
+
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if line.trim().is_empty() && !in_code {
+            if !lines.is_empty() {
+                paragraphs.push(lines.join("\n"));
+                lines.clear();
+            }
+            continue;
+        }
+        lines.push(line);
+    }
+    if !lines.is_empty() {
+        paragraphs.push(lines.join("\n"));
+    }
+
+    paragraphs
+}
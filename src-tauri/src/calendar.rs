@@ -0,0 +1,297 @@
+use crate::time_context;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeDelta};
+use entity::anniversary;
+use serde::Serialize;
+
+// 默认列出此天数内的日子
+pub const DEFAULT_UPCOMING_DAYS: i64 = 30;
+
+// 导出的文件名
+pub const ICS_FILE_NAME: &str = "momotalk-plus.ics";
+
+// ICS文件每行的最大字节数，超出时折行
+const ICS_LINE_OCTETS: usize = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    // 学生的生日，来自学生资料
+    Birthday,
+    // 第一次聊天的纪念日，来自对话的第一条消息
+    FirstChat,
+    // 用户自定义的纪念日
+    Anniversary,
+}
+
+// 每年重复的日子
+#[derive(Debug, Clone)]
+pub struct EventSource {
+    pub kind: EventKind,
+    pub title: String,
+    // 为空时与所有学生相关
+    pub student_id: Option<i32>,
+    pub student_name: Option<String>,
+    pub month: u32,
+    pub day: u32,
+    // 起始年份，用于计算周年数，此年份及之前不算作纪念日
+    pub since_year: Option<i32>,
+    // 当天注入prompt的内容，为空时使用默认内容
+    pub prompt: Option<String>,
+    pub anniversary_id: Option<i32>,
+}
+
+// 某个日子的下一次到来
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarEvent {
+    pub kind: EventKind,
+    pub title: String,
+    pub student_id: Option<i32>,
+    pub student_name: Option<String>,
+    pub date: NaiveDate,
+    pub days_until: i64,
+    // 第几周年
+    pub years: Option<i32>,
+    pub prompt: Option<String>,
+    pub anniversary_id: Option<i32>,
+}
+
+impl EventSource {
+    pub fn birthday(student_id: i32, student_name: &str, month: u32, day: u32) -> Self {
+        Self {
+            kind: EventKind::Birthday,
+            title: format!("{}的生日", student_name),
+            student_id: Some(student_id),
+            student_name: Some(student_name.to_string()),
+            month,
+            day,
+            since_year: None,
+            prompt: None,
+            anniversary_id: None,
+        }
+    }
+
+    // first_chat_at为对话中第一条消息的时间，按本地日期计算
+    pub fn first_chat(
+        student_id: i32,
+        student_name: &str,
+        first_chat_at: DateTime<FixedOffset>,
+    ) -> Self {
+        let date = first_chat_at.with_timezone(&chrono::Local).date_naive();
+        Self {
+            kind: EventKind::FirstChat,
+            title: format!("与{}相识的纪念日", student_name),
+            student_id: Some(student_id),
+            student_name: Some(student_name.to_string()),
+            month: date.month(),
+            day: date.day(),
+            since_year: Some(date.year()),
+            prompt: None,
+            anniversary_id: None,
+        }
+    }
+
+    // 月或日不合法时返回None
+    pub fn anniversary(model: &anniversary::Model, student_name: Option<String>) -> Option<Self> {
+        Some(Self {
+            kind: EventKind::Anniversary,
+            title: model.title.clone(),
+            student_id: model.student_id,
+            student_name,
+            month: u32::try_from(model.month).ok()?,
+            day: u32::try_from(model.day).ok()?,
+            since_year: model.year,
+            prompt: model.prompt.clone().filter(|p| !p.trim().is_empty()),
+            anniversary_id: Some(model.id),
+        })
+    }
+
+    // 今天或之后最近的一次，有起始年份时从起始年份的次年开始计算
+    pub fn next_occurrence(&self, today: NaiveDate) -> Option<CalendarEvent> {
+        let from = match self.since_year {
+            Some(year) if today.year() <= year => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+            _ => today,
+        };
+        let days = time_context::days_until_birthday(from, self.month, self.day)?;
+        let date = from + TimeDelta::days(days);
+
+        Some(CalendarEvent {
+            kind: self.kind,
+            title: self.title.clone(),
+            student_id: self.student_id,
+            student_name: self.student_name.clone(),
+            date,
+            days_until: (date - today).num_days(),
+            years: self.since_year.map(|year| date.year() - year),
+            prompt: self.prompt.clone(),
+            anniversary_id: self.anniversary_id,
+        })
+    }
+}
+
+impl CalendarEvent {
+    // 是否与某个学生相关
+    pub fn concerns(&self, student_id: i32) -> bool {
+        self.student_id.is_none_or(|id| id == student_id)
+    }
+
+    // 以学生的视角描述这个日子
+    fn occasion(&self) -> String {
+        match (self.kind, self.years) {
+            (EventKind::Birthday, _) => "你的生日".to_string(),
+            (EventKind::FirstChat, Some(years)) => {
+                format!("你和用户第一次聊天的{}周年纪念日", years)
+            }
+            (EventKind::FirstChat, None) => "你和用户第一次聊天的纪念日".to_string(),
+            (EventKind::Anniversary, Some(years)) => {
+                format!("「{}」（{}周年）", self.title, years)
+            }
+            (EventKind::Anniversary, _) => format!("「{}」", self.title),
+        }
+    }
+}
+
+// 检查自定义纪念日的名称与日期，允许2月29日
+pub fn validate_anniversary(title: &str, month: i32, day: i32) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("Anniversary title is required".to_string());
+    }
+
+    let date = u32::try_from(month)
+        .ok()
+        .zip(u32::try_from(day).ok())
+        .and_then(|(month, day)| NaiveDate::from_ymd_opt(2000, month, day));
+    if date.is_none() {
+        return Err(format!("Invalid anniversary date: {}-{}", month, day));
+    }
+
+    Ok(())
+}
+
+// 列出days天内的日子，按日期排序
+pub fn upcoming(sources: &[EventSource], today: NaiveDate, days: i64) -> Vec<CalendarEvent> {
+    let mut events: Vec<CalendarEvent> = sources
+        .iter()
+        .filter_map(|source| source.next_occurrence(today))
+        .filter(|event| event.days_until <= days)
+        .collect();
+    events.sort_by(|a, b| (a.days_until, &a.title).cmp(&(b.days_until, &b.title)));
+
+    events
+}
+
+// 今天的日子在prompt中的文本
+pub fn format_today(events: &[CalendarEvent]) -> String {
+    events
+        .iter()
+        .map(|event| match &event.prompt {
+            Some(prompt) => prompt.clone(),
+            None if event.kind == EventKind::Birthday => {
+                "今天是你的生日。用户可能会为你庆祝，可以自然地表现出期待或开心。".to_string()
+            }
+            None => format!("今天是{}，可以在对话中自然地提起。", event.occasion()),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 让LLM在特别的日子主动问候的指令，作为最后一条用户消息发送
+pub fn greeting_instruction(student_name: &str, events: &[&CalendarEvent]) -> String {
+    let occasions = events
+        .iter()
+        .map(|event| event.occasion())
+        .collect::<Vec<_>>()
+        .join("、");
+
+    format!(
+        "（系统提示：今天是{occasions}。请以{name}的身份主动给用户发一条MomoTalk消息，\
+围绕今天这个日子自然地打招呼、分享心情或送上祝福，语气符合你们现在的关系。\
+只输出消息内容，简短一些，不要提及这条提示。）",
+        occasions = occasions,
+        name = student_name
+    )
+}
+
+// 导出为每年重复的全天日程，可导入到系统日历
+pub fn to_ics(sources: &[EventSource], today: NaiveDate, stamp: DateTime<FixedOffset>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//MomoTalk Plus//Calendar//ZH".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+
+    for source in sources {
+        // 有起始年份时从那一年开始，否则从今年开始，2月29日从最近的闰年开始
+        let start = source
+            .since_year
+            .and_then(|year| NaiveDate::from_ymd_opt(year, source.month, source.day))
+            .or_else(|| {
+                (0..4).find_map(|n| {
+                    NaiveDate::from_ymd_opt(today.year() - n, source.month, source.day)
+                })
+            });
+        let Some(start) = start else {
+            continue;
+        };
+
+        // 2月29日在平年按2月最后一天重复，多数日历不支持顺延到3月1日
+        let rule = if source.month == 2 && source.day == 29 {
+            "RRULE:FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=-1"
+        } else {
+            "RRULE:FREQ=YEARLY"
+        };
+
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@momotalk-plus", event_uid(source)),
+            format!("DTSTAMP:{}", stamp.to_utc().format("%Y%m%dT%H%M%SZ")),
+            format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")),
+            rule.to_string(),
+            format!("SUMMARY:{}", escape_ics(&source.title)),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_ics_line(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+fn event_uid(source: &EventSource) -> String {
+    match (source.kind, source.anniversary_id, source.student_id) {
+        (EventKind::Anniversary, Some(id), _) => format!("anniversary-{}", id),
+        (EventKind::Birthday, _, Some(id)) => format!("birthday-{}", id),
+        (EventKind::FirstChat, _, Some(id)) => format!("first-chat-{}", id),
+        _ => format!("{}-{}", source.month, source.day),
+    }
+}
+
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// 按字节数折行，续行以空格开头，不在多字节字符中间断开
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+
+    for c in line.chars() {
+        let size = c.len_utf8();
+        if length + size > ICS_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += size;
+    }
+
+    folded
+}
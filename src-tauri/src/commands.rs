@@ -1,7 +1,22 @@
-use crate::db;
-use entity::{conversation, message};
+use crate::{
+    avatar, avatar_cache, bond, bubble, calendar, db, delivery, diff, lore, memory, proactive,
+    prompt, retrieval, suggestion,
+    time_context::{self, Clock},
+    title, vector, world_info,
+};
+use chrono::NaiveDate;
+use entity::{
+    anniversary, conversation, lorebook, lorebook_entry, message, persona, prompt_template,
+    student, student_memory, student_prompt_revision,
+};
+use llm::embedding::EmbeddingConfig;
 use llm::model::MessageData;
-use tauri::State;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager, State};
 use tauri_plugin_store::StoreExt;
 
 // API Key 管理命令
@@ -32,6 +47,1129 @@ pub async fn get_store(
     Ok(key.and_then(|v| v.as_str().map(|s| s.to_string())))
 }
 
+fn custom_avatar_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?;
+    Ok(dir.join(avatar::CUSTOM_AVATAR_DIR))
+}
+
+// 下载或重新验证所有学生的远程头像，供离线使用
+#[tauri::command]
+pub async fn prefetch_avatars(
+    db_client: State<'_, db::DbClient>,
+    cache: State<'_, avatar_cache::AvatarCache>,
+) -> Result<avatar_cache::PrefetchReport, String> {
+    let students = db::get_students(&db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut urls: Vec<String> = Vec::new();
+    for student in students {
+        for url in avatar::parse_avatars(&student.avatars) {
+            if avatar_cache::is_remote(&url) && !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+
+    Ok(cache.prefetch(urls).await)
+}
+
+// 学生资料相关命令
+#[tauri::command]
+pub async fn get_student_profile(
+    student_id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Option<db::StudentWithProfile>, String> {
+    db::get_student_profile(&db_client, student_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 获取启动状态，包括数据集同步结果与降级原因
+#[tauri::command]
+pub async fn get_startup_status(
+    status: State<'_, db::StartupStatus>,
+) -> Result<db::StartupStatus, String> {
+    Ok(status.inner().clone())
+}
+
+// 自定义角色相关命令
+#[tauri::command]
+pub async fn create_custom_student(
+    mut data: db::CustomStudentData,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<student::Model, String> {
+    let dir = custom_avatar_dir(&app_handle)?;
+    data.avatars = avatar::import_custom_avatars(&dir, &data.avatars).map_err(|e| e.to_string())?;
+    let avatars = data.avatars.clone();
+
+    match db::create_custom_student(&db_client, data).await {
+        Ok(student) => Ok(student),
+        Err(e) => {
+            // 创建失败时清理已复制的头像
+            avatar::remove_custom_avatars(&dir, &avatars);
+            Err(e.to_string())
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn update_custom_student(
+    id: i32,
+    mut data: db::CustomStudentUpdateData,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<student::Model, String> {
+    let dir = custom_avatar_dir(&app_handle)?;
+    let old_avatars = db::get_student_by_id(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|s| avatar::parse_avatars(&s.avatars))
+        .unwrap_or_default();
+
+    if let Some(sources) = &data.avatars {
+        data.avatars =
+            Some(avatar::import_custom_avatars(&dir, sources).map_err(|e| e.to_string())?);
+    }
+    let new_avatars = data.avatars.clone();
+
+    let student = db::update_custom_student(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // 清理被替换掉的旧头像
+    if let Some(new_avatars) = new_avatars {
+        let removed: Vec<String> = old_avatars
+            .into_iter()
+            .filter(|a| !new_avatars.contains(a))
+            .collect();
+        avatar::remove_custom_avatars(&dir, &removed);
+    }
+
+    Ok(student)
+}
+
+#[tauri::command]
+pub async fn delete_custom_student(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<student::Model, String> {
+    let student = db::delete_custom_student(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let dir = custom_avatar_dir(&app_handle)?;
+    avatar::remove_custom_avatars(&dir, &avatar::parse_avatars(&student.avatars));
+
+    Ok(student)
+}
+
+// 使用模板为学生渲染system prompt，未指定模板时使用学生当前的模板
+//
+// lore为检索到的背景资料片段，为None时注入完整的档案
+async fn render_student_prompt(
+    db_client: &db::DbClient,
+    student: &student::Model,
+    template_id: Option<i32>,
+    persona: Option<&persona::Model>,
+    lore: Option<&str>,
+    today: NaiveDate,
+    app_handle: &tauri::AppHandle,
+) -> Result<String, String> {
+    let template = match template_id {
+        Some(id) => db::get_prompt_template_by_id(db_client, id)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Prompt template not found".to_string())?,
+        None => db::get_prompt_template_for_student(db_client, student)
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+
+    // 优先使用人设的名字，没有人设时使用设置中的用户名
+    let user_name = match persona {
+        Some(persona) => persona.name.clone(),
+        None => get_store("username".to_string(), app_handle.clone())
+            .await
+            .unwrap_or(None)
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| prompt::DEFAULT_USER_NAME.to_string()),
+    };
+
+    let profile = db::get_student_profile(db_client, student.id)
+        .await
+        .map_err(|e| e.to_string())?
+        .and_then(|p| p.profile);
+
+    prompt::render_for_student(
+        &template.content,
+        student,
+        profile.as_ref(),
+        lore.unwrap_or(&student.content),
+        &user_name,
+        today,
+    )
+    .map_err(|e| e.to_string())
+}
+
+// 向量化接口配置，未设置embedding_api_key时不启用向量检索
+async fn embedding_config(app_handle: &tauri::AppHandle) -> Option<EmbeddingConfig> {
+    let setting = |key: &str| {
+        let key = key.to_string();
+        async move {
+            get_store(key, app_handle.clone())
+                .await
+                .unwrap_or(None)
+                .filter(|v| !v.is_empty())
+        }
+    };
+
+    let mut config = EmbeddingConfig::new(setting("embedding_api_key").await?);
+    if let Some(base_url) = setting("embedding_base_url").await {
+        config.base_url = base_url;
+    }
+    if let Some(model) = setting("embedding_model").await {
+        config.model = model;
+    }
+
+    Some(config)
+}
+
+// 将单条文本向量化，失败时返回None
+async fn embed_text(text: &str, config: &EmbeddingConfig) -> Option<Vec<f32>> {
+    match llm::embedding::create_embeddings(vec![text.to_string()], config).await {
+        Ok(mut vectors) => vectors.pop(),
+        Err(e) => {
+            println!("向量化失败: {}", e);
+            None
+        }
+    }
+}
+
+// 在后台为消息或记忆生成并保存向量
+async fn index_embedding(
+    db_client: db::DbClient,
+    config: EmbeddingConfig,
+    source_type: &'static str,
+    source_id: i32,
+    text: String,
+) {
+    let Some(values) = embed_text(&text, &config).await else {
+        return;
+    };
+
+    if let Err(e) =
+        db::save_embedding(&db_client, source_type, source_id, &config.model, &values).await
+    {
+        println!("[ERROR] 保存向量失败: {}", e);
+    }
+}
+
+// 用户最新的消息，用于挑选要注入prompt的相关内容
+struct PromptQuery<'a> {
+    text: &'a str,
+    // 消息的向量与生成它的模型
+    embedding: Option<(&'a str, &'a [f32])>,
+    // 超出发送窗口的较早消息，从中检索相关的过往对话
+    earlier_messages: &'a [message::Model],
+    // 发送窗口内的历史消息，用于触发世界书词条
+    recent_messages: &'a [message::Model],
+    // 用户上一条消息的时间
+    last_message_at: Option<DateTimeWithTimeZone>,
+    // 回复的拆分方式，使用分隔符时在prompt中说明格式
+    split_mode: bubble::SplitMode,
+    // 当前时间的来源
+    clock: &'a dyn time_context::Clock,
+}
+
+// 时间段落的设置，time_awareness为false时不注入，time_locale为注入内容的语言
+async fn time_context_config(app_handle: &tauri::AppHandle) -> time_context::TimeContextConfig {
+    let enabled = get_store("time_awareness".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .is_none_or(|v| v != "false");
+    let locale = get_store("time_locale".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .map(|tag| time_context::Locale::from_tag(&tag))
+        .unwrap_or_default();
+
+    time_context::TimeContextConfig { enabled, locale }
+}
+
+// 组装对话的system prompt
+async fn build_system_prompt(
+    db_client: &db::DbClient,
+    conversation: &db::ConversationWithStudent,
+    query: &PromptQuery<'_>,
+    app_handle: &tauri::AppHandle,
+) -> Result<String, String> {
+    let persona = db::get_persona_for_conversation(db_client, &conversation.conversation)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // 只注入与当前消息相关的背景资料，没有建立索引时使用完整档案
+    let chunks = db::get_lore_chunks(db_client, conversation.student.id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let lore = (!chunks.is_empty()).then(|| {
+        let passages = lore::select_passages(&chunks, query.text, lore::MAX_LORE_PASSAGES);
+        println!(
+            "注入的背景资料片段: {:?}",
+            passages.iter().map(|p| p.position).collect::<Vec<_>>()
+        );
+        lore::format_passages(&passages)
+    });
+
+    let student_prompt = render_student_prompt(
+        db_client,
+        &conversation.student,
+        None,
+        persona.as_ref(),
+        lore.as_deref(),
+        query.clock.now().date_naive(),
+        app_handle,
+    )
+    .await?;
+
+    // 扫描最近的消息，触发世界书词条
+    let lorebooks = db::get_lorebooks_for_student(db_client, conversation.student.id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let scanned: Vec<&str> = query
+        .recent_messages
+        .iter()
+        .map(|m| m.content.as_str())
+        .chain([query.text])
+        .collect();
+    let entries = world_info::select_entries(&lorebooks, &scanned);
+
+    let mut sections = vec![
+        prompt::PromptSection::titled(
+            "世界设定",
+            world_info::format_entries(&entries, world_info::POSITION_BEFORE),
+        ),
+        prompt::PromptSection::new(student_prompt),
+        prompt::PromptSection::titled(
+            "相关设定",
+            world_info::format_entries(&entries, world_info::POSITION_AFTER),
+        ),
+    ];
+
+    if let Some(persona) = &persona {
+        sections.push(prompt::persona_section(persona));
+    }
+
+    // 注入与当前消息最相关的长期记忆
+    let memories = db::get_student_memories(db_client, conversation.student.id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut similarities = HashMap::new();
+    if let Some((model, query_vector)) = query.embedding {
+        let ids = memories.iter().map(|m| m.id).collect();
+        let embeddings = db::get_embeddings(db_client, db::EMBEDDING_SOURCE_MEMORY, ids, model)
+            .await
+            .map_err(|e| e.to_string())?;
+        similarities.extend(
+            embeddings
+                .into_iter()
+                .map(|(id, v)| (id, vector::cosine_similarity(query_vector, &v))),
+        );
+    }
+    let memories = memory::select_relevant(
+        memories,
+        query.text,
+        &similarities,
+        query.clock.now(),
+        memory::MAX_INJECTED_MEMORIES,
+    );
+    if !memories.is_empty() {
+        sections.push(prompt::PromptSection::titled(
+            "你记得的事",
+            memory::format_memories(&memories),
+        ));
+        db::mark_memories_used(db_client, memories.iter().map(|m| m.id).collect())
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    // 检索与当前消息相似的较早对话
+    if let Some((model, query_vector)) = query.embedding {
+        let ids = query
+            .earlier_messages
+            .iter()
+            .filter(|m| m.role == "assistant")
+            .map(|m| m.id)
+            .collect::<Vec<_>>();
+        let embeddings = db::get_embeddings(db_client, db::EMBEDDING_SOURCE_MESSAGE, ids, model)
+            .await
+            .map_err(|e| e.to_string())?;
+        let reply_ids: Vec<i32> = vector::top_k(
+            query_vector,
+            embeddings,
+            retrieval::MAX_RETRIEVED_EXCHANGES,
+            retrieval::MIN_SIMILARITY,
+        )
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+        if !reply_ids.is_empty() {
+            sections.push(prompt::PromptSection::titled(
+                "相关的过往对话",
+                retrieval::format_exchanges(
+                    query.earlier_messages,
+                    &reply_ids,
+                    &conversation.student.name,
+                ),
+            ));
+        }
+    }
+
+    // 根据羁绊等级调整学生的语气
+    let bond = db::get_student_bond(db_client, conversation.student.id)
+        .await
+        .map_err(|e| e.to_string())?;
+    sections.push(prompt::PromptSection::titled(
+        "羁绊",
+        bond::tone_guidance(bond.map_or(1, |b| b.level)),
+    ));
+
+    // 选择了带服装名称的头像时，告知模型当前的装扮
+    let variant = avatar::selected_variant(
+        &conversation.student,
+        conversation.conversation.avatar.as_deref(),
+    );
+    if let Some(label) = variant.and_then(|v| v.label) {
+        sections.push(prompt::PromptSection::titled(
+            "当前装扮",
+            format!(
+                "{}现在的装扮是「{}」，可以在对话中自然地体现出来。",
+                conversation.student.name, label
+            ),
+        ));
+    }
+
+    // 告知学生当前的时间、用户离开了多久以及自己的生日
+    let time_config = time_context_config(app_handle).await;
+    if time_config.enabled {
+        let profile = db::get_student_profile(db_client, conversation.student.id)
+            .await
+            .map_err(|e| e.to_string())?
+            .and_then(|p| p.profile);
+        let birthday = profile.and_then(|p| {
+            let month = u32::try_from(p.birthday_month?).ok()?;
+            let day = u32::try_from(p.birthday_day?).ok()?;
+            Some((month, day))
+        });
+        sections.push(prompt::PromptSection::titled(
+            "当前时间",
+            time_context::describe(
+                query.clock,
+                time_config.locale,
+                &time_context::TimeContext {
+                    student_name: &conversation.student.name,
+                    birthday,
+                    last_message_at: query.last_message_at,
+                },
+            ),
+        ));
+    }
+
+    // 学生的生日、第一次聊天的纪念日等特别的日子
+    let today = query.clock.now().date_naive();
+    let today_events = events_today(db_client, conversation.student.id, today).await?;
+    if !today_events.is_empty() {
+        sections.push(prompt::PromptSection::titled(
+            "今天的特别日子",
+            calendar::format_today(&today_events),
+        ));
+    }
+
+    if query.split_mode == bubble::SplitMode::Delimiter {
+        sections.push(prompt::PromptSection::titled(
+            "消息格式",
+            bubble::format_instruction(),
+        ));
+    }
+
+    if let Some(prompt_override) = &conversation.conversation.prompt_override {
+        sections.push(prompt::PromptSection::titled(
+            "本次对话的补充设定（优先于以上设定）",
+            prompt_override.clone(),
+        ));
+    }
+
+    Ok(prompt::assemble_system_prompt(sections))
+}
+
+// 今天与指定学生相关的日子
+async fn events_today(
+    db_client: &db::DbClient,
+    student_id: i32,
+    today: NaiveDate,
+) -> Result<Vec<calendar::CalendarEvent>, String> {
+    let sources = db::get_calendar_sources(db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(calendar::upcoming(&sources, today, 0)
+        .into_iter()
+        .filter(|event| event.concerns(student_id))
+        .collect())
+}
+
+// 用户最后一条消息的时间，学生的回复与主动消息不算在内
+fn last_user_message_at(messages: &[message::Model]) -> Option<DateTimeWithTimeZone> {
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.created_at)
+}
+
+// 羁绊相关命令
+#[tauri::command]
+pub async fn get_student_bond(
+    student_id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<bond::BondStatus, String> {
+    let bond = db::get_student_bond(&db_client, student_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(bond::BondStatus::new(
+        student_id,
+        bond.map_or(0, |b| b.points),
+    ))
+}
+
+#[tauri::command]
+pub async fn get_student_bonds(
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<bond::BondStatus>, String> {
+    let bonds = db::get_student_bonds(&db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(bonds
+        .into_iter()
+        .map(|b| bond::BondStatus::new(b.student_id, b.points))
+        .collect())
+}
+
+// 为一轮对话累积羁绊点数，传入api_key时由LLM评分，否则按固定点数
+async fn award_bond_points(
+    db_client: db::DbClient,
+    student: student::Model,
+    user_message: String,
+    reply: String,
+    api_key: Option<String>,
+) {
+    let points = match api_key {
+        Some(api_key) => score_exchange(&student.name, &user_message, &reply, api_key)
+            .await
+            .unwrap_or(bond::POINTS_PER_EXCHANGE),
+        None => bond::POINTS_PER_EXCHANGE,
+    };
+
+    match db::add_student_bond_points(&db_client, student.id, points).await {
+        Ok((bond, previous_level)) if bond.level > previous_level => {
+            println!("与{}的羁绊等级提升至 {}", student.name, bond.level);
+        }
+        Ok(_) => {}
+        Err(e) => println!("[ERROR] 更新羁绊失败: {}", e),
+    }
+}
+
+// 由LLM为一轮对话评分，失败时返回None
+async fn score_exchange(
+    student_name: &str,
+    user_message: &str,
+    reply: &str,
+    api_key: String,
+) -> Option<i32> {
+    let messages = vec![MessageData {
+        role: "user".to_string(),
+        content: bond::scoring_prompt(student_name, user_message, reply),
+    }];
+
+    match llm::chat::chat_with_ds_model(messages, Some(api_key), llm::chat::CHAT_MODEL).await {
+        Ok(response) => bond::parse_score(&response.content),
+        Err(e) => {
+            println!("羁绊评分失败: {}", e);
+            None
+        }
+    }
+}
+
+// 记忆相关命令
+#[tauri::command]
+pub async fn get_student_memories(
+    student_id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<student_memory::Model>, String> {
+    db::get_student_memories(&db_client, student_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_student_memory(
+    student_id: i32,
+    data: db::MemoryData,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<student_memory::Model, String> {
+    let memory = db::create_student_memory(&db_client, student_id, data)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(config) = embedding_config(&app_handle).await {
+        tauri::async_runtime::spawn(index_embedding(
+            db_client.inner().clone(),
+            config,
+            db::EMBEDDING_SOURCE_MEMORY,
+            memory.id,
+            memory.content.clone(),
+        ));
+    }
+
+    Ok(memory)
+}
+
+#[tauri::command]
+pub async fn update_student_memory(
+    id: i32,
+    data: db::MemoryUpdateData,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<student_memory::Model, String> {
+    let content_changed = data.content.is_some();
+    let memory = db::update_student_memory(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(config) = embedding_config(&app_handle)
+        .await
+        .filter(|_| content_changed)
+    {
+        tauri::async_runtime::spawn(index_embedding(
+            db_client.inner().clone(),
+            config,
+            db::EMBEDDING_SOURCE_MEMORY,
+            memory.id,
+            memory.content.clone(),
+        ));
+    }
+
+    Ok(memory)
+}
+
+#[tauri::command]
+pub async fn delete_student_memory(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<student_memory::Model, String> {
+    db::delete_student_memory(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 由LLM从一轮对话中提取长期记忆并保存
+async fn extract_memories(
+    db_client: db::DbClient,
+    student: student::Model,
+    user_message: String,
+    reply: String,
+    api_key: String,
+    embedding: Option<EmbeddingConfig>,
+) {
+    let existing = match db::get_student_memories(&db_client, student.id).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            println!("[ERROR] 读取记忆失败: {}", e);
+            return;
+        }
+    };
+
+    let messages = vec![MessageData {
+        role: "user".to_string(),
+        content: memory::extraction_prompt(&student.name, &user_message, &reply, &existing),
+    }];
+    let output =
+        match llm::chat::chat_with_ds_model(messages, Some(api_key), llm::chat::CHAT_MODEL).await {
+            Ok(response) => response.content,
+            Err(e) => {
+                println!("提取记忆失败: {}", e);
+                return;
+            }
+        };
+
+    let facts = match memory::parse_extraction(&output) {
+        Ok(facts) => facts,
+        Err(e) => {
+            println!("解析记忆失败: {}. 原始输出: {}", e, output);
+            return;
+        }
+    };
+
+    match db::save_extracted_memories(&db_client, student.id, facts).await {
+        Ok(saved) if !saved.is_empty() => {
+            println!("为{}保存了 {} 条新记忆", student.name, saved.len());
+            if let Some(config) = embedding {
+                for memory in saved {
+                    index_embedding(
+                        db_client.clone(),
+                        config.clone(),
+                        db::EMBEDDING_SOURCE_MEMORY,
+                        memory.id,
+                        memory.content,
+                    )
+                    .await;
+                }
+            }
+        }
+        Ok(_) => {}
+        Err(e) => println!("[ERROR] 保存记忆失败: {}", e),
+    }
+}
+
+// 世界书相关命令
+#[tauri::command]
+pub async fn get_lorebooks(
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<lorebook::Model>, String> {
+    db::get_lorebooks(&db_client)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_lorebook(
+    data: db::LorebookData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<lorebook::Model, String> {
+    db::create_lorebook(&db_client, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_lorebook(
+    id: i32,
+    data: db::LorebookUpdateData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<lorebook::Model, String> {
+    db::update_lorebook(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_lorebook(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<lorebook::Model, String> {
+    db::delete_lorebook(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_lorebook_entries(
+    lorebook_id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<lorebook_entry::Model>, String> {
+    db::get_lorebook_entries(&db_client, lorebook_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_lorebook_entry(
+    lorebook_id: i32,
+    data: db::LorebookEntryData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<lorebook_entry::Model, String> {
+    db::create_lorebook_entry(&db_client, lorebook_id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_lorebook_entry(
+    id: i32,
+    data: db::LorebookEntryUpdateData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<lorebook_entry::Model, String> {
+    db::update_lorebook_entry(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_lorebook_entry(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<lorebook_entry::Model, String> {
+    db::delete_lorebook_entry(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 日历相关命令
+#[tauri::command]
+pub async fn get_upcoming_events(
+    days: Option<i64>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<calendar::CalendarEvent>, String> {
+    let sources = db::get_calendar_sources(&db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(calendar::upcoming(
+        &sources,
+        time_context::SystemClock.now().date_naive(),
+        days.unwrap_or(calendar::DEFAULT_UPCOMING_DAYS).max(0),
+    ))
+}
+
+#[tauri::command]
+pub async fn get_anniversaries(
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<anniversary::Model>, String> {
+    db::get_anniversaries(&db_client)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_anniversary(
+    data: db::AnniversaryData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<anniversary::Model, String> {
+    db::create_anniversary(&db_client, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_anniversary(
+    id: i32,
+    data: db::AnniversaryUpdateData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<anniversary::Model, String> {
+    db::update_anniversary(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_anniversary(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<anniversary::Model, String> {
+    db::delete_anniversary(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 将日历导出为.ics文件，保存到下载目录，返回文件路径
+#[tauri::command]
+pub async fn export_calendar_ics(
+    app_handle: tauri::AppHandle,
+    db_client: State<'_, db::DbClient>,
+) -> Result<String, String> {
+    let sources = db::get_calendar_sources(&db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+    let now = time_context::SystemClock.now();
+    let content = calendar::to_ics(&sources, now.date_naive(), now);
+
+    let dir = app_handle
+        .path()
+        .download_dir()
+        .or_else(|_| app_handle.path().app_data_dir())
+        .map_err(|e| e.to_string())?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let path = dir.join(calendar::ICS_FILE_NAME);
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+// 用户人设相关命令
+#[tauri::command]
+pub async fn get_personas(
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<persona::Model>, String> {
+    db::get_personas(&db_client)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_persona(
+    data: db::PersonaData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<persona::Model, String> {
+    db::create_persona(&db_client, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_persona(
+    id: i32,
+    data: db::PersonaUpdateData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<persona::Model, String> {
+    db::update_persona(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_persona(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<persona::Model, String> {
+    db::delete_persona(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_active_persona(
+    id: Option<i32>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Option<persona::Model>, String> {
+    db::set_active_persona(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_conversation_persona(
+    id: String,
+    persona_id: Option<i32>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<conversation::Model, String> {
+    db::set_conversation_persona(&db_client, id, persona_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 头像相关命令
+#[tauri::command]
+pub async fn get_student_avatar_variants(
+    student_id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<avatar::AvatarVariant>, String> {
+    let student = db::get_student_by_id(&db_client, student_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Student not found".to_string())?;
+
+    Ok(avatar::avatar_variants(&student))
+}
+
+#[tauri::command]
+pub async fn set_conversation_avatar(
+    id: String,
+    avatar: Option<String>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<conversation::Model, String> {
+    db::set_conversation_avatar(&db_client, id, avatar)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// prompt模板相关命令
+#[tauri::command]
+pub async fn get_prompt_templates(
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<prompt_template::Model>, String> {
+    db::get_prompt_templates(&db_client)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_prompt_template(
+    data: db::PromptTemplateData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<prompt_template::Model, String> {
+    db::create_prompt_template(&db_client, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_prompt_template(
+    id: i32,
+    data: db::PromptTemplateUpdateData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<prompt_template::Model, String> {
+    db::update_prompt_template(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_prompt_template(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<prompt_template::Model, String> {
+    db::delete_prompt_template(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_student_prompt_template(
+    student_id: i32,
+    template_id: Option<i32>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<student::Model, String> {
+    db::set_student_prompt_template(&db_client, student_id, template_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 设置学生的打字速度（字/分钟），传入None使用默认速度
+#[tauri::command]
+pub async fn set_student_typing_speed(
+    student_id: i32,
+    typing_speed: Option<i32>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<student::Model, String> {
+    db::set_student_typing_speed(&db_client, student_id, typing_speed)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 预览模板为指定学生渲染后的结果
+#[tauri::command]
+pub async fn preview_prompt_template(
+    student_id: i32,
+    template_id: Option<i32>,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let student = db::get_student_by_id(&db_client, student_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Student not found".to_string())?;
+
+    let persona = db::get_active_persona(&db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    render_student_prompt(
+        &db_client,
+        &student,
+        template_id,
+        persona.as_ref(),
+        None,
+        time_context::SystemClock.now().date_naive(),
+        &app_handle,
+    )
+    .await
+}
+
+// 查看指定消息会检索到的背景资料片段，用于调试
+#[tauri::command]
+pub async fn debug_lore_passages(
+    student_id: i32,
+    query: String,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<lore::LorePassage>, String> {
+    let chunks = db::get_lore_chunks(&db_client, student_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(lore::select_passages(
+        &chunks,
+        &query,
+        lore::MAX_LORE_PASSAGES,
+    ))
+}
+
+// prompt版本相关命令
+#[tauri::command]
+pub async fn update_student_prompt(
+    student_id: i32,
+    prompt: String,
+    note: Option<String>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<student::Model, String> {
+    db::update_student_prompt(&db_client, student_id, prompt, note)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_student_prompt_revisions(
+    student_id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<student_prompt_revision::Model>, String> {
+    db::get_student_prompt_revisions(&db_client, student_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 比较两个prompt版本，未指定目标版本时与学生当前的prompt比较
+#[tauri::command]
+pub async fn diff_student_prompt_revisions(
+    from_revision_id: i32,
+    to_revision_id: Option<i32>,
+    db_client: State<'_, db::DbClient>,
+) -> Result<Vec<diff::DiffLine>, String> {
+    let from = db::get_student_prompt_revision_by_id(&db_client, from_revision_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Prompt revision not found".to_string())?;
+
+    let to_prompt = match to_revision_id {
+        Some(id) => {
+            db::get_student_prompt_revision_by_id(&db_client, id)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "Prompt revision not found".to_string())?
+                .prompt
+        }
+        None => {
+            db::get_student_by_id(&db_client, from.student_id)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "Student not found".to_string())?
+                .prompt
+        }
+    };
+
+    Ok(diff::diff_lines(&from.prompt, &to_prompt))
+}
+
+#[tauri::command]
+pub async fn restore_student_prompt_revision(
+    revision_id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<student::Model, String> {
+    db::restore_student_prompt_revision(&db_client, revision_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 // 数据库相关命令
 #[tauri::command]
 pub async fn get_conversations(
@@ -83,29 +1221,41 @@ pub async fn delete_conversation(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn mark_conversation_read(
+    id: String,
+    db_client: State<'_, db::DbClient>,
+) -> Result<conversation::Model, String> {
+    db::mark_conversation_read(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 // 消息相关命令
 #[tauri::command]
 pub async fn get_messages_by_conversation_id(
     conversation_id: String,
     db_client: State<'_, db::DbClient>,
 ) -> Result<Vec<message::Model>, String> {
-    db::get_messages_by_conversation_id(&db_client, conversation_id)
+    db::get_visible_messages_by_conversation_id(&db_client, conversation_id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_messages_by_conversation_id_with_pagination(
+pub async fn get_messages_by_conversation_id_with_cursor(
     conversation_id: String,
-    page: u64,
-    page_size: u64,
+    before_index: Option<i32>,
+    after_index: Option<i32>,
+    limit: u64,
     db_client: State<'_, db::DbClient>,
-) -> Result<Vec<message::Model>, String> {
-    db::get_messages_by_conversation_id_with_pagination(
+) -> Result<db::MessagePage, String> {
+    db::get_messages_by_conversation_id_with_cursor(
         &db_client,
         conversation_id,
-        page,
-        page_size,
+        before_index,
+        after_index,
+        limit,
     )
     .await
     .map_err(|e| e.to_string())
@@ -121,6 +1271,61 @@ pub async fn create_message(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn update_message(
+    id: i32,
+    data: db::MessageUpdateData,
+    db_client: State<'_, db::DbClient>,
+) -> Result<message::Model, String> {
+    db::update_message(&db_client, id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_message(
+    id: i32,
+    db_client: State<'_, db::DbClient>,
+) -> Result<message::Model, String> {
+    db::delete_message(&db_client, id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// 合并连续的相同角色消息
+fn merge_same_role(messages: &[MessageData]) -> Vec<MessageData> {
+    let mut processed_messages: Vec<MessageData> = Vec::new();
+
+    for msg in messages {
+        match processed_messages.last_mut() {
+            // 如果当前消息与前一条消息角色相同，则合并内容
+            Some(last_msg) if last_msg.role == msg.role => {
+                last_msg.content = format!("{}\n\n{}", last_msg.content, msg.content);
+            }
+            // 添加新消息
+            _ => processed_messages.push(MessageData {
+                role: msg.role.clone(),
+                content: msg.content.clone(),
+            }),
+        }
+    }
+
+    processed_messages
+}
+
+// chat_with_llm的返回值，content为完整的回复，bubbles为拆分后依次保存的消息
+#[derive(Debug, Serialize)]
+pub struct ChatReply {
+    pub role: String,
+    pub content: String,
+    pub turn_id: String,
+    pub bubbles: Vec<String>,
+    // 为true时消息通过message-delivered事件逐条送达
+    pub staggered: bool,
+    // 用户下一条消息的候选回复，未开启快捷回复时为空
+    pub suggestions: Vec<String>,
+}
+
 // 聊天相关命令
 #[tauri::command]
 pub async fn chat_with_llm(
@@ -128,41 +1333,85 @@ pub async fn chat_with_llm(
     conversation_id: String,
     db_client: State<'_, db::DbClient>,
     app_handle: tauri::AppHandle,
-) -> Result<MessageData, String> {
+) -> Result<ChatReply, String> {
     // 获取对话历史
     let mut history = db::get_messages_by_conversation_id(&db_client, conversation_id.clone())
         .await
         .map_err(|e| e.to_string())?;
 
-    // 检查是否存在system消息
-    let has_system_message = history.iter().any(|msg| msg.role == "system");
+    let conversation = db::get_conversation_by_id(&db_client, conversation_id.clone())
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Conversation not found".to_string())?;
 
-    // 如果没有system消息，则创建一个
-    if !has_system_message {
-        println!("未找到system消息，创建默认system消息");
-        let conversation = db::get_conversation_by_id(&db_client, conversation_id.clone())
-            .await
-            .map_err(|e| e.to_string())?;
-        let student = conversation.unwrap().student;
-        let system_message_data = db::MessageData {
-            conversation_id: conversation_id.clone(),
-            role: "system".to_string(),
-            content: student.prompt.clone(),
-            name: None,
-            index: Some(0), // 确保system消息排在最前面
-        };
+    // system prompt不再保存为消息，每次请求时重新组装
+    history.retain(|msg| msg.role != "system");
 
-        // 将system消息保存到数据库
-        let system_message = db::create_message(&db_client, system_message_data)
-            .await
-            .map_err(|e| e.to_string())?;
+    let last_message_at = last_user_message_at(&history);
+
+    let embedding = embedding_config(&app_handle).await;
+    let query_vector = match &embedding {
+        Some(config) => embed_text(&message.content, config).await,
+        None => None,
+    };
+
+    // 能够进行向量检索时只发送最近的消息，更早的消息中能检索到的轮次通过检索补充，
+    // 其余的消息以及无法检索时的完整历史照常发送
+    let mut earlier_messages: Vec<message::Model> = Vec::new();
+    if let Some(config) = embedding.as_ref().filter(|_| query_vector.is_some()) {
+        let window_start = history.len().saturating_sub(retrieval::HISTORY_WINDOW);
+        let reply_ids = history[..window_start]
+            .iter()
+            .filter(|m| m.role == "assistant")
+            .map(|m| m.id)
+            .collect();
+        let embedded_ids = db::get_embedded_source_ids(
+            &db_client,
+            db::EMBEDDING_SOURCE_MESSAGE,
+            reply_ids,
+            &config.model,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
 
-        // 将系统消息添加到历史记录中
-        history.insert(0, system_message);
+        let mut retrievable =
+            retrieval::retrievable(&history[..window_start], &embedded_ids).into_iter();
+        (earlier_messages, history) = history
+            .into_iter()
+            .partition(|_| retrievable.next().unwrap_or(false));
     }
 
-    // 构造消息列表，包含历史消息和新消息
-    let mut messages = Vec::new();
+    let split_mode = bubble::SplitMode::from_setting(
+        get_store("reply_bubbles".to_string(), app_handle.clone())
+            .await
+            .unwrap_or(None)
+            .as_deref(),
+    );
+
+    let query = PromptQuery {
+        text: &message.content,
+        embedding: embedding
+            .as_ref()
+            .zip(query_vector.as_deref())
+            .map(|(config, values)| (config.model.as_str(), values)),
+        earlier_messages: &earlier_messages,
+        recent_messages: &history,
+        last_message_at,
+        split_mode,
+        clock: &time_context::SystemClock,
+    };
+    let system_prompt = build_system_prompt(&db_client, &conversation, &query, &app_handle).await?;
+
+    // 记录本次回复使用的prompt版本
+    let prompt_revision_id = db::get_current_prompt_revision_id(&db_client, &conversation.student)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // 构造消息列表，包含system prompt、历史消息和新消息
+    let mut messages = vec![MessageData {
+        role: "system".to_string(),
+        content: system_prompt,
+    }];
 
     // 检查是否需要合并连续的用户消息
     let mut merged_content = message.content.clone();
@@ -200,30 +1449,7 @@ pub async fn chat_with_llm(
     messages.push(merged_message);
 
     // 处理消息列表，确保没有连续的相同角色消息
-    let mut processed_messages = Vec::new();
-    let mut prev_role = String::new();
-
-    for msg in &messages {
-        // 如果当前消息与前一条消息角色相同，则合并内容
-        if !processed_messages.is_empty() && msg.role == prev_role {
-            let last_index = processed_messages.len() - 1;
-            let last_msg: &MessageData = &processed_messages[last_index];
-            let merged_content = format!("{}\n\n{}", last_msg.content.clone(), msg.content.clone());
-
-            // 替换最后一条消息
-            processed_messages[last_index] = MessageData {
-                role: msg.role.clone(),
-                content: merged_content,
-            };
-        } else {
-            // 添加新消息
-            processed_messages.push(MessageData {
-                role: msg.role.clone(),
-                content: msg.content.clone(),
-            });
-            prev_role = msg.role.clone();
-        }
-    }
+    let processed_messages = merge_same_role(&messages);
 
     println!(
         "原始消息数量: {}, 处理后的消息数量: {}",
@@ -231,6 +1457,8 @@ pub async fn chat_with_llm(
         processed_messages.len()
     );
 
+    let read_delay = delivery::read_delay(&message.content);
+
     // 将用户消息保存到数据库
     let user_message_data = db::MessageData {
         conversation_id: conversation_id.clone(),
@@ -238,20 +1466,40 @@ pub async fn chat_with_llm(
         content: message.content,
         name: None,
         index: None,
+        prompt_revision_id: None,
+        proactive: false,
+        turn_id: None,
     };
 
     db::create_message(&db_client, user_message_data)
         .await
         .map_err(|e| e.to_string())?;
 
-    let api_key = get_store("api_key".to_string(), app_handle)
+    // 开启送达模拟时，学生在后台读到消息并开始输入，与请求模型同时进行
+    let message_delivery = get_store("message_delivery".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .is_none_or(|v| v != "false");
+    let read_task = message_delivery.then(|| {
+        tauri::async_runtime::spawn(read_message(
+            app_handle.clone(),
+            db_client.inner().clone(),
+            conversation_id.clone(),
+            read_delay,
+        ))
+    });
+
+    let api_key = get_store("api_key".to_string(), app_handle.clone())
         .await
         .unwrap_or(None);
 
     // 调用 llm crate 中的聊天功能，使用处理后的消息列表
-    let response = match llm::chat::chat_with_ds(processed_messages, api_key).await {
+    let response = match llm::chat::chat_with_ds(processed_messages, api_key.clone()).await {
         Ok(r) => r,
         Err(e) => {
+            if let Some(read_task) = &read_task {
+                read_task.abort();
+            }
             let error_msg = format!("LLM聊天失败: {}", e);
             println!("[ERROR] {}", error_msg);
             return Err(error_msg);
@@ -260,23 +1508,603 @@ pub async fn chat_with_llm(
 
     println!("LLM响应: {:?}", response);
 
-    // 创建消息数据
-    let message_data = db::MessageData {
-        conversation_id,
-        role: response.role.clone(),
-        content: response.content.clone(),
-        name: None,
-        index: None,
-    };
+    // 拆分为多条消息，之后的处理使用去掉分隔符的完整回复
+    let bubbles = bubble::split_reply(&response.content, split_mode);
+    let content = bubbles.join("\n");
 
-    // 将回复保存到数据库
-    db::create_message(&db_client, message_data)
+    // 将回复依次保存到数据库
+    let reply_messages = bubbles
+        .iter()
+        .map(|bubble| db::MessageData {
+            conversation_id: conversation_id.clone(),
+            role: response.role.clone(),
+            content: bubble.clone(),
+            name: None,
+            index: None,
+            prompt_revision_id: Some(prompt_revision_id),
+            proactive: false,
+            turn_id: None,
+        })
+        .collect();
+    let replies = db::create_turn_messages(&db_client, reply_messages)
         .await
         .map_err(|e| e.to_string())?;
+    let turn_id = replies
+        .first()
+        .and_then(|reply| reply.turn_id.clone())
+        .unwrap_or_default();
+
+    // 在后台为本轮对话生成向量，挂在最后一条消息上，供之后检索
+    if let (Some(config), Some(reply)) = (embedding.clone(), replies.last()) {
+        tauri::async_runtime::spawn(index_embedding(
+            db_client.inner().clone(),
+            config,
+            db::EMBEDDING_SOURCE_MESSAGE,
+            reply.id,
+            retrieval::exchange_text(&merged_content, &content),
+        ));
+    }
+
+    // 开启送达模拟时，在后台按学生的打字速度逐条送达，否则立即标记为已读与已送达
+    let staggered = read_task.is_some();
+    match read_task {
+        Some(read_task) => {
+            tauri::async_runtime::spawn(deliver_replies(
+                app_handle.clone(),
+                db_client.inner().clone(),
+                turn_id.clone(),
+                replies,
+                conversation.student.typing_speed,
+                read_task,
+            ));
+        }
+        None => {
+            db::mark_user_messages_read(&db_client, conversation_id.clone())
+                .await
+                .map_err(|e| e.to_string())?;
+            db::mark_messages_delivered(&db_client, replies.iter().map(|r| r.id).collect())
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    // 开启快捷回复时，为用户的下一条消息生成候选，候选不保存到历史
+    let quick_replies = get_store("quick_replies".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .is_some_and(|v| v == "true");
+    let suggestions = if quick_replies {
+        let mut dialogue: Vec<(&str, &str)> = history
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !skip_indices.contains(i))
+            .map(|(_, m)| (m.role.as_str(), m.content.as_str()))
+            .collect();
+        dialogue.push(("user", &merged_content));
+        dialogue.push(("assistant", &content));
+        let dialogue = &dialogue[dialogue.len().saturating_sub(suggestion::CONTEXT_MESSAGES)..];
+
+        let model = auxiliary_model(&app_handle, "quick_reply_model").await;
+        generate_suggestions(
+            &conversation.student.name,
+            dialogue,
+            api_key.clone(),
+            &model,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            println!("[ERROR] {}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+
+    // 在后台累积本轮对话的羁绊点数，不阻塞回复
+    let llm_scoring = get_store("bond_llm_scoring".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .is_some_and(|v| v == "true");
+    tauri::async_runtime::spawn(award_bond_points(
+        db_client.inner().clone(),
+        conversation.student.clone(),
+        merged_content.clone(),
+        content.clone(),
+        llm_scoring.then(|| api_key.clone()).flatten(),
+    ));
+
+    // 在后台根据最初几轮对话生成标题，用户锁定的标题不会被覆盖
+    let auto_title = get_store("auto_title".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .is_none_or(|v| v != "false");
+    if auto_title && !conversation.conversation.title_locked {
+        tauri::async_runtime::spawn(auto_title_conversation(
+            app_handle.clone(),
+            db_client.inner().clone(),
+            conversation_id,
+            api_key.clone(),
+        ));
+    }
+
+    // 在后台从本轮对话中提取长期记忆
+    let memory_extraction = get_store("memory_extraction".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .is_none_or(|v| v != "false");
+    if let Some(api_key) = api_key.filter(|_| memory_extraction) {
+        tauri::async_runtime::spawn(extract_memories(
+            db_client.inner().clone(),
+            conversation.student.clone(),
+            merged_content,
+            content.clone(),
+            api_key,
+            embedding,
+        ));
+    }
 
     // 返回响应
-    Ok(MessageData {
+    Ok(ChatReply {
         role: response.role,
-        content: response.content,
+        content,
+        turn_id,
+        bubbles,
+        staggered,
+        suggestions,
     })
 }
+
+// 候选回复、标题等辅助任务使用的模型，对应的设置为空时使用响应更快的对话模型
+async fn auxiliary_model(app_handle: &tauri::AppHandle, key: &str) -> String {
+    get_store(key.to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .filter(|model| !model.trim().is_empty())
+        .unwrap_or_else(|| llm::chat::CHAT_MODEL.to_string())
+}
+
+// 请求LLM为用户生成候选回复，dialogue为按时间顺序排列的(角色, 内容)
+async fn generate_suggestions(
+    student_name: &str,
+    dialogue: &[(&str, &str)],
+    api_key: Option<String>,
+    model: &str,
+) -> Result<Vec<String>, String> {
+    let messages = vec![MessageData {
+        role: "user".to_string(),
+        content: suggestion::suggestion_prompt(student_name, dialogue),
+    }];
+    let output = llm::chat::chat_with_ds_model(messages, api_key, model)
+        .await
+        .map_err(|e| format!("生成候选回复失败: {}", e))?
+        .content;
+
+    suggestion::parse_suggestions(&output)
+        .map_err(|e| format!("解析候选回复失败: {}. 原始输出: {}", e, output))
+}
+
+// 在对话的前几轮自动更新标题，更新后通知前端
+async fn auto_title_conversation(
+    app_handle: tauri::AppHandle,
+    db_client: db::DbClient,
+    conversation_id: String,
+    api_key: Option<String>,
+) {
+    let model = auxiliary_model(&app_handle, "title_model").await;
+    match generate_title(&db_client, conversation_id, api_key, &model, false).await {
+        Ok(Some(conversation)) => emit_event(
+            &app_handle,
+            title::CONVERSATION_TITLE_EVENT,
+            title::ConversationTitleEvent {
+                conversation_id: conversation.id,
+                title: conversation.title,
+            },
+        ),
+        Ok(None) => {}
+        Err(e) => println!("[ERROR] {}", e),
+    }
+}
+
+// 根据最初几轮对话生成标题并保存，生成的标题不锁定
+//
+// force为false时只在前几轮对话中更新，且不覆盖用户锁定的标题，无需更新时返回None
+async fn generate_title(
+    db_client: &db::DbClient,
+    conversation_id: String,
+    api_key: Option<String>,
+    model: &str,
+    force: bool,
+) -> Result<Option<conversation::Model>, String> {
+    let conversation = db::get_conversation_by_id(db_client, conversation_id.clone())
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Conversation not found".to_string())?;
+    if !force && conversation.conversation.title_locked {
+        return Ok(None);
+    }
+
+    let mut history = db::get_messages_by_conversation_id(db_client, conversation_id.clone())
+        .await
+        .map_err(|e| e.to_string())?;
+    history.retain(|msg| msg.role != "system");
+
+    let exchanges = title::exchange_count(&history);
+    if force && exchanges == 0 {
+        return Err("Conversation has no exchanges to summarize".to_string());
+    }
+    if !force && !(1..=title::TITLE_EXCHANGES).contains(&exchanges) {
+        return Ok(None);
+    }
+
+    let dialogue: Vec<(&str, &str)> = title::first_exchanges(&history, title::TITLE_EXCHANGES)
+        .iter()
+        .map(|m| (m.role.as_str(), m.content.as_str()))
+        .collect();
+    let messages = vec![MessageData {
+        role: "user".to_string(),
+        content: title::title_prompt(&conversation.student.name, &dialogue),
+    }];
+    let output = llm::chat::chat_with_ds_model(messages, api_key, model)
+        .await
+        .map_err(|e| format!("生成标题失败: {}", e))?
+        .content;
+    let title = title::clean_title(&output)
+        .ok_or_else(|| format!("生成标题失败，无法解析输出: {}", output))?;
+
+    // 生成期间用户可能手动修改了标题
+    if !force {
+        let locked = db::get_conversation_by_id(db_client, conversation_id.clone())
+            .await
+            .map_err(|e| e.to_string())?
+            .is_none_or(|c| c.conversation.title_locked);
+        if locked {
+            return Ok(None);
+        }
+    }
+
+    let data = db::ConversationUpdateData {
+        title: Some(title),
+        title_locked: Some(false),
+        prompt_override: None,
+    };
+    db::update_conversation(db_client, conversation_id, data)
+        .await
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+// 根据最初几轮对话重新生成标题，会覆盖并解锁用户设置的标题
+#[tauri::command]
+pub async fn generate_conversation_title(
+    id: String,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<conversation::Model, String> {
+    let api_key = get_store("api_key".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None);
+    let model = auxiliary_model(&app_handle, "title_model").await;
+
+    generate_title(&db_client, id, api_key, &model, true)
+        .await?
+        .ok_or_else(|| "Conversation title was not updated".to_string())
+}
+
+// 根据最近的聊天记录为用户生成候选回复，不保存到历史
+#[tauri::command]
+pub async fn suggest_replies(
+    conversation_id: String,
+    db_client: State<'_, db::DbClient>,
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<String>, String> {
+    let conversation = db::get_conversation_by_id(&db_client, conversation_id.clone())
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Conversation not found".to_string())?;
+
+    let mut history = db::get_messages_by_conversation_id(&db_client, conversation_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    history.retain(|msg| msg.role != "system");
+    let dialogue: Vec<(&str, &str)> = history
+        [history.len().saturating_sub(suggestion::CONTEXT_MESSAGES)..]
+        .iter()
+        .map(|m| (m.role.as_str(), m.content.as_str()))
+        .collect();
+
+    let api_key = get_store("api_key".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None);
+    let model = auxiliary_model(&app_handle, "quick_reply_model").await;
+
+    generate_suggestions(&conversation.student.name, &dialogue, api_key, &model).await
+}
+
+// 等待学生读到用户的消息，随后开始输入，返回开始输入的时间
+async fn read_message(
+    app_handle: tauri::AppHandle,
+    db_client: db::DbClient,
+    conversation_id: String,
+    delay: Duration,
+) -> Instant {
+    tokio::time::sleep(delay).await;
+
+    match db::mark_user_messages_read(&db_client, conversation_id.clone()).await {
+        Ok(read_at) => emit_event(
+            &app_handle,
+            delivery::MESSAGE_READ_EVENT,
+            delivery::MessageReadEvent {
+                conversation_id: conversation_id.clone(),
+                read_at,
+            },
+        ),
+        Err(e) => println!("[ERROR] 标记已读失败: {}", e),
+    }
+    emit_event(
+        &app_handle,
+        delivery::MESSAGE_TYPING_EVENT,
+        delivery::MessageTypingEvent { conversation_id },
+    );
+
+    Instant::now()
+}
+
+// 按学生的打字速度逐条送达回复，第一条在等待模型回复期间已经开始输入
+async fn deliver_replies(
+    app_handle: tauri::AppHandle,
+    db_client: db::DbClient,
+    turn_id: String,
+    replies: Vec<message::Model>,
+    typing_speed: Option<i32>,
+    read_task: tauri::async_runtime::JoinHandle<Instant>,
+) {
+    let typing_since = read_task.await.unwrap_or_else(|_| Instant::now());
+    let total = replies.len();
+
+    for (index, reply) in replies.into_iter().enumerate() {
+        let typing = delivery::typing_duration(&reply.content, typing_speed);
+        if index == 0 {
+            tokio::time::sleep(typing.saturating_sub(typing_since.elapsed())).await;
+        } else {
+            emit_event(
+                &app_handle,
+                delivery::MESSAGE_TYPING_EVENT,
+                delivery::MessageTypingEvent {
+                    conversation_id: reply.conversation_id.clone(),
+                },
+            );
+            tokio::time::sleep(typing).await;
+        }
+
+        // 送达前消息被删除时仍按原内容通知前端
+        let message = match db::mark_messages_delivered(&db_client, vec![reply.id]).await {
+            Ok(mut messages) => messages.pop().unwrap_or(reply),
+            Err(e) => {
+                println!("[ERROR] 标记送达失败: {}", e);
+                reply
+            }
+        };
+        emit_event(
+            &app_handle,
+            delivery::MESSAGE_DELIVERED_EVENT,
+            delivery::MessageDeliveredEvent {
+                conversation_id: message.conversation_id.clone(),
+                turn_id: turn_id.clone(),
+                index,
+                total,
+                message,
+            },
+        );
+    }
+}
+
+fn emit_event<S: Serialize + Clone>(app_handle: &tauri::AppHandle, event: &str, payload: S) {
+    if let Err(e) = app_handle.emit(event, payload) {
+        println!("[ERROR] 发送{}事件失败: {}", event, e);
+    }
+}
+
+// 主动消息的设置
+async fn proactive_config(app_handle: &tauri::AppHandle) -> proactive::ProactiveConfig {
+    let keys = [
+        "proactive_messages",
+        "proactive_check_minutes",
+        "proactive_min_interval_minutes",
+        "proactive_daily_limit",
+        "proactive_inactive_hours",
+        "proactive_quiet_start",
+        "proactive_quiet_end",
+        "proactive_students",
+    ];
+
+    let mut settings = HashMap::new();
+    for key in keys {
+        if let Some(value) = get_store(key.to_string(), app_handle.clone())
+            .await
+            .unwrap_or(None)
+        {
+            settings.insert(key, value);
+        }
+    }
+
+    proactive::ProactiveConfig::from_settings(&settings)
+}
+
+// 定时检查是否由学生主动发起对话，应用启动时在后台运行
+pub async fn run_proactive_scheduler(app_handle: tauri::AppHandle, db_client: db::DbClient) {
+    loop {
+        let config = proactive_config(&app_handle).await;
+        if config.enabled {
+            let clock = time_context::SystemClock;
+            if let Err(e) = send_proactive_message(&app_handle, &db_client, &config, &clock).await {
+                println!("[ERROR] 发送主动消息失败: {}", e);
+            }
+        }
+
+        tokio::time::sleep(Duration::from_secs(config.check_interval_minutes * 60)).await;
+    }
+}
+
+// 挑选一名学生，由LLM生成开场白并保存为未读消息
+async fn send_proactive_message(
+    app_handle: &tauri::AppHandle,
+    db_client: &db::DbClient,
+    config: &proactive::ProactiveConfig,
+    clock: &dyn time_context::Clock,
+) -> Result<(), String> {
+    let now = clock.now();
+    if config.in_quiet_hours(now.time()) {
+        return Ok(());
+    }
+
+    let recent_sent: Vec<_> =
+        db::get_proactive_messages_since(db_client, now - chrono::TimeDelta::hours(24))
+            .await
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|m| m.created_at)
+            .collect();
+    if config.rate_limited(now, &recent_sent) {
+        return Ok(());
+    }
+
+    let Some(api_key) = get_store("api_key".to_string(), app_handle.clone())
+        .await
+        .unwrap_or(None)
+        .filter(|key| !key.is_empty())
+    else {
+        return Ok(());
+    };
+
+    let bond_levels: HashMap<i32, i32> = db::get_student_bonds(db_client)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|b| (b.student_id, b.level))
+        .collect();
+
+    // 今天的特别日子，当天还没有聊过天的学生会优先发送问候
+    let today = now.date_naive();
+    let sources = db::get_calendar_sources(db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+    let today_events = calendar::upcoming(&sources, today, 0);
+
+    // 只考虑聊过天且用户已读完的对话
+    let conversations = db::get_conversations(db_client)
+        .await
+        .map_err(|e| e.to_string())?;
+    let candidates = conversations
+        .iter()
+        .filter(|c| c.unread_count == 0)
+        .filter_map(|c| {
+            let last_message_at = c.last_message_at?;
+            Some(proactive::Candidate {
+                conversation_id: c.conversation.id.clone(),
+                student_id: c.student.id,
+                last_message_at,
+                bond_level: bond_levels.get(&c.student.id).copied().unwrap_or(1),
+                occasion: last_message_at.with_timezone(&now.timezone()).date_naive() < today
+                    && today_events.iter().any(|e| e.concerns(c.student.id)),
+            })
+        })
+        .collect();
+    let jitter = || f64::from(uuid::Uuid::new_v4().as_u128() as u16) / f64::from(u16::MAX);
+    let Some(candidate) = proactive::pick_candidate(candidates, now, config, jitter) else {
+        return Ok(());
+    };
+    let Some(conversation) = conversations
+        .into_iter()
+        .find(|c| c.conversation.id == candidate.conversation_id)
+    else {
+        return Ok(());
+    };
+
+    let mut history =
+        db::get_messages_by_conversation_id(db_client, candidate.conversation_id.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+    history.retain(|msg| msg.role != "system");
+    let recent = history.split_off(history.len().saturating_sub(retrieval::HISTORY_WINDOW));
+
+    let query = PromptQuery {
+        text: "",
+        embedding: None,
+        earlier_messages: &history,
+        recent_messages: &recent,
+        last_message_at: last_user_message_at(&recent).or_else(|| last_user_message_at(&history)),
+        split_mode: bubble::SplitMode::Off,
+        clock,
+    };
+    let system_prompt = build_system_prompt(db_client, &conversation, &query, app_handle).await?;
+
+    let mut messages = vec![MessageData {
+        role: "system".to_string(),
+        content: system_prompt,
+    }];
+    messages.extend(recent.iter().map(|msg| MessageData {
+        role: msg.role.clone(),
+        content: msg.content.clone(),
+    }));
+    let occasions: Vec<_> = today_events
+        .iter()
+        .filter(|e| candidate.occasion && e.concerns(candidate.student_id))
+        .collect();
+    messages.push(MessageData {
+        role: "user".to_string(),
+        content: if occasions.is_empty() {
+            proactive::opener_instruction(&conversation.student.name)
+        } else {
+            calendar::greeting_instruction(&conversation.student.name, &occasions)
+        },
+    });
+
+    let response = llm::chat::chat_with_ds_model(
+        merge_same_role(&messages),
+        Some(api_key),
+        llm::chat::CHAT_MODEL,
+    )
+    .await
+    .map_err(|e| format!("LLM聊天失败: {}", e))?;
+    let content = response.content.trim().to_string();
+    if content.is_empty() {
+        return Err("LLM返回了空消息".to_string());
+    }
+
+    let prompt_revision_id = db::get_current_prompt_revision_id(db_client, &conversation.student)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // 作为未读的助手消息保存，未读数随之增加
+    let proactive_message = db::create_message(
+        db_client,
+        db::MessageData {
+            conversation_id: candidate.conversation_id.clone(),
+            role: "assistant".to_string(),
+            content: content.clone(),
+            name: None,
+            index: None,
+            prompt_revision_id: Some(prompt_revision_id),
+            proactive: true,
+            turn_id: None,
+        },
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    db::mark_messages_delivered(db_client, vec![proactive_message.id])
+        .await
+        .map_err(|e| e.to_string())?;
+
+    println!("{}主动发送了一条消息", conversation.student.name);
+
+    app_handle
+        .emit(
+            proactive::PROACTIVE_MESSAGE_EVENT,
+            proactive::ProactiveMessageEvent {
+                conversation_id: candidate.conversation_id,
+                student_id: conversation.student.id,
+                content,
+            },
+        )
+        .map_err(|e| e.to_string())
+}
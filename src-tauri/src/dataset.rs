@@ -0,0 +1,530 @@
+use crate::{lore, profile};
+use entity::prelude::{AppMeta, LoreChunk, Student, StudentProfile};
+use entity::{app_meta, conversation, lore_chunk, student, student_profile};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, Set, TransactionTrait,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// app_meta中记录已导入数据集版本的键
+const DATASET_VERSION_KEY: &str = "dataset_version";
+
+// 内置数据集文件的路径
+pub struct DatasetPaths {
+    pub manifest: PathBuf,
+    pub students_content: PathBuf,
+    pub students_data: PathBuf,
+}
+
+// 数据集同步结果，供前端展示
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DatasetSyncReport {
+    pub previous_version: Option<String>,
+    pub current_version: String,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub skipped: Vec<String>,
+    // 被跳过或存在问题的数据集记录
+    pub issues: Vec<DatasetIssue>,
+}
+
+// 数据集中单条记录的问题
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetIssue {
+    pub file: String,
+    pub index: usize,
+    pub name: Option<String>,
+    pub message: String,
+}
+
+// 导致数据集无法同步的错误
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_json::Error),
+    Db(DbErr),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(path, e) => write!(f, "无法读取数据集文件 {}: {}", path.display(), e),
+            DatasetError::Parse(path, e) => {
+                write!(f, "无法解析数据集文件 {}: {}", path.display(), e)
+            }
+            DatasetError::Db(e) => write!(f, "同步数据集失败: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+impl From<DbErr> for DatasetError {
+    fn from(e: DbErr) -> Self {
+        DatasetError::Db(e)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DatasetManifest {
+    version: String,
+}
+
+// students_min.json中的记录
+#[derive(Debug, Deserialize)]
+struct StudentDataRecord {
+    id: u64,
+    // 带服装名称的显示名，如「阿露（正月）」
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "personalName")]
+    personal_name: String,
+    // 结构化的学生资料，存在时优先于从档案文本中解析的内容
+    #[serde(flatten)]
+    profile: profile::StructuredProfile,
+}
+
+// students.json中的记录
+#[derive(Debug, Deserialize)]
+struct StudentContentRecord {
+    name: String,
+    content: String,
+}
+
+// 合并同名学生后的数据集记录
+struct DatasetStudent {
+    name: String,
+    avatars: Vec<String>,
+    // 头像地址对应的服装名称
+    avatar_labels: BTreeMap<String, String>,
+    content: String,
+    profile: profile::StructuredProfile,
+}
+
+// 将内置数据集同步到数据库
+//
+// 数据集版本与数据库中记录的版本一致时跳过；否则新增缺少的学生，
+// 更新官方学生的头像、资料，不会改动学生的prompt与自定义角色。
+pub async fn sync_students(
+    conn: &DatabaseConnection,
+    paths: &DatasetPaths,
+) -> Result<DatasetSyncReport, DatasetError> {
+    let current_version = read_manifest(&paths.manifest)?.version;
+    let previous_version = AppMeta::find_by_id(DATASET_VERSION_KEY)
+        .one(conn)
+        .await?
+        .map(|m| m.value);
+
+    let mut report = DatasetSyncReport {
+        previous_version: previous_version.clone(),
+        current_version: current_version.clone(),
+        ..Default::default()
+    };
+
+    let has_students = Student::find().one(conn).await?.is_some();
+    if has_students && previous_version.as_deref() == Some(current_version.as_str()) {
+        return Ok(report);
+    }
+
+    println!(
+        "同步学生数据集: {} -> {}",
+        previous_version.as_deref().unwrap_or("无"),
+        current_version
+    );
+    let students = load_dataset(
+        &paths.students_content,
+        &paths.students_data,
+        &mut report.issues,
+    )?;
+
+    let txn = conn.begin().await?;
+
+    for dataset_student in students {
+        let existing = Student::find()
+            .filter(student::Column::Name.eq(&dataset_student.name))
+            .one(&txn)
+            .await?;
+
+        match existing {
+            // 自定义角色与官方学生重名时，保留自定义角色不做改动
+            Some(existing) if existing.is_custom => {
+                println!("已存在同名自定义角色 {}，跳过导入", existing.name);
+                report.skipped.push(existing.name);
+            }
+            Some(existing) => {
+                if update_student(&txn, existing, &dataset_student).await? {
+                    println!("已更新学生 {}", dataset_student.name);
+                    report.updated.push(dataset_student.name);
+                }
+            }
+            None => {
+                insert_student(&txn, &dataset_student).await?;
+                println!("已创建学生 {} 及对应对话", dataset_student.name);
+                report.added.push(dataset_student.name);
+            }
+        }
+    }
+
+    AppMeta::insert(app_meta::ActiveModel {
+        key: Set(DATASET_VERSION_KEY.to_string()),
+        value: Set(current_version),
+    })
+    .on_conflict(
+        OnConflict::column(app_meta::Column::Key)
+            .update_column(app_meta::Column::Value)
+            .to_owned(),
+    )
+    .exec(&txn)
+    .await?;
+
+    txn.commit().await?;
+
+    println!(
+        "数据集同步完成: 新增 {} 个，更新 {} 个，跳过 {} 个，问题记录 {} 条",
+        report.added.len(),
+        report.updated.len(),
+        report.skipped.len(),
+        report.issues.len()
+    );
+    Ok(report)
+}
+
+fn read_manifest(path: &Path) -> Result<DatasetManifest, DatasetError> {
+    let file = std::fs::File::open(path).map_err(|e| DatasetError::Io(path.to_path_buf(), e))?;
+    serde_json::from_reader(file).map_err(|e| DatasetError::Parse(path.to_path_buf(), e))
+}
+
+// 读取JSON数组文件，逐条解析记录，无法解析的记录记为问题并跳过
+fn read_records<T: DeserializeOwned>(
+    path: &Path,
+    issues: &mut Vec<DatasetIssue>,
+) -> Result<Vec<(usize, T)>, DatasetError> {
+    let file = std::fs::File::open(path).map_err(|e| DatasetError::Io(path.to_path_buf(), e))?;
+    let values: Vec<serde_json::Value> = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|e| DatasetError::Parse(path.to_path_buf(), e))?;
+
+    let mut records = Vec::with_capacity(values.len());
+    for (index, value) in values.into_iter().enumerate() {
+        let name = value
+            .get("personalName")
+            .or_else(|| value.get("name"))
+            .and_then(|n| n.as_str())
+            .map(|n| n.to_string());
+
+        match serde_json::from_value::<T>(value) {
+            Ok(record) => records.push((index, record)),
+            Err(e) => issues.push(issue(path, index, name, e.to_string())),
+        }
+    }
+
+    Ok(records)
+}
+
+fn issue(path: &Path, index: usize, name: Option<String>, message: String) -> DatasetIssue {
+    DatasetIssue {
+        file: path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        index,
+        name,
+        message,
+    }
+}
+
+// 读取数据集，按personalName合并同一学生的多个头像
+fn load_dataset(
+    students_content_path: &Path,
+    students_data_path: &Path,
+    issues: &mut Vec<DatasetIssue>,
+) -> Result<Vec<DatasetStudent>, DatasetError> {
+    let data_records: Vec<(usize, StudentDataRecord)> = read_records(students_data_path, issues)?;
+    let content_records: Vec<(usize, StudentContentRecord)> =
+        read_records(students_content_path, issues)?;
+
+    // 学生的背景资料
+    let mut student_contents: HashMap<String, String> = HashMap::new();
+    for (index, record) in content_records {
+        let name = record.name.trim().to_string();
+        let message = if name.is_empty() {
+            Some("学生名称为空")
+        } else if record.content.trim().is_empty() {
+            Some("背景资料为空")
+        } else if student_contents.contains_key(&name) {
+            Some("学生名称重复")
+        } else {
+            None
+        };
+
+        match message {
+            Some(message) => issues.push(issue(
+                students_content_path,
+                index,
+                Some(record.name),
+                message.to_string(),
+            )),
+            None => {
+                student_contents.insert(name, record.content);
+            }
+        }
+    }
+
+    let mut students: Vec<DatasetStudent> = Vec::new();
+    let mut seen_ids = HashSet::new();
+    for (index, record) in data_records {
+        let student_name = record.personal_name.trim().to_string();
+        if student_name.is_empty() {
+            issues.push(issue(
+                students_data_path,
+                index,
+                None,
+                "personalName为空".to_string(),
+            ));
+            continue;
+        }
+        if !seen_ids.insert(record.id) {
+            issues.push(issue(
+                students_data_path,
+                index,
+                Some(student_name),
+                format!("id {} 重复", record.id),
+            ));
+            continue;
+        }
+
+        let avatar_url = format!(
+            "https://aronacdn.hanasaki.tech/images/student/icon/{}.webp",
+            record.id
+        );
+
+        let label = variant_label(&record.name, &student_name);
+
+        match students.iter_mut().find(|s| s.name == student_name) {
+            Some(existing) => {
+                if let Some(label) = label {
+                    existing.avatar_labels.insert(avatar_url.clone(), label);
+                }
+                existing.avatars.push(avatar_url);
+            }
+            None => {
+                let content = student_contents.get(&student_name).cloned();
+                if content.is_none() {
+                    issues.push(issue(
+                        students_data_path,
+                        index,
+                        Some(student_name.clone()),
+                        "缺少该学生的背景资料".to_string(),
+                    ));
+                }
+                students.push(DatasetStudent {
+                    name: student_name,
+                    avatar_labels: label
+                        .map(|label| BTreeMap::from([(avatar_url.clone(), label)]))
+                        .unwrap_or_default(),
+                    avatars: vec![avatar_url],
+                    content: content.unwrap_or_default(),
+                    profile: record.profile,
+                });
+            }
+        }
+    }
+
+    Ok(students)
+}
+
+// 从显示名中取出服装名称，如「阿露（正月）」中的「正月」
+fn variant_label(name: &str, personal_name: &str) -> Option<String> {
+    let name = name.trim();
+    if name.is_empty() || name == personal_name {
+        return None;
+    }
+
+    let label = name
+        .split_once('（')
+        .or_else(|| name.split_once('('))
+        .map(|(_, rest)| rest.trim_end_matches(['）', ')']).trim())
+        .unwrap_or(name);
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+// 创建新学生及对应私聊
+async fn insert_student<C: ConnectionTrait>(
+    conn: &C,
+    dataset_student: &DatasetStudent,
+) -> Result<(), DbErr> {
+    let student_model = student::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        name: Set(dataset_student.name.clone()),
+        avatars: Set(serde_json::to_string(&dataset_student.avatars).unwrap_or_default()),
+        avatar_labels: Set(
+            serde_json::to_string(&dataset_student.avatar_labels).unwrap_or_default()
+        ),
+        prompt: Set(String::new()),
+        is_custom: Set(false),
+        metadata: Set("{}".to_string()),
+        content: Set(dataset_student.content.clone()),
+        prompt_template_id: Set(None),
+        typing_speed: Set(None),
+    };
+
+    let student = student_model.insert(conn).await?;
+    save_student_profile(conn, student.id, &student.content, &dataset_student.profile).await?;
+    save_lore_chunks(conn, student.id, &student.content).await?;
+
+    // 为每个学生创建对话
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+
+    let conversation = conversation::ActiveModel {
+        id: Set(uuid::Uuid::new_v4().to_string()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        title: Set(student.name.clone()),
+        student_name: Set(student.name.clone()),
+        last_read_index: Set(-1),
+        prompt_override: Set(None),
+        avatar: Set(None),
+        persona_id: Set(None),
+        title_locked: Set(false),
+    };
+
+    conversation.insert(conn).await?;
+
+    Ok(())
+}
+
+// 更新已有官方学生的头像与资料，返回是否有改动
+async fn update_student<C: ConnectionTrait>(
+    conn: &C,
+    existing: student::Model,
+    dataset_student: &DatasetStudent,
+) -> Result<bool, DbErr> {
+    let avatars = serde_json::to_string(&dataset_student.avatars).unwrap_or_default();
+    let avatar_labels = serde_json::to_string(&dataset_student.avatar_labels).unwrap_or_default();
+    let avatars_changed = existing.avatars != avatars || existing.avatar_labels != avatar_labels;
+    let content_changed = existing.content != dataset_student.content;
+    let has_lore = LoreChunk::find()
+        .filter(lore_chunk::Column::StudentId.eq(existing.id))
+        .one(conn)
+        .await?
+        .is_some();
+
+    // 资料同时来自档案文本与结构化字段，每次同步都重新保存
+    save_student_profile(
+        conn,
+        existing.id,
+        &dataset_student.content,
+        &dataset_student.profile,
+    )
+    .await?;
+
+    if !avatars_changed && !content_changed {
+        if !has_lore {
+            save_lore_chunks(conn, existing.id, &existing.content).await?;
+        }
+        return Ok(false);
+    }
+
+    let mut student_model: student::ActiveModel = existing.into();
+    student_model.avatars = Set(avatars);
+    student_model.avatar_labels = Set(avatar_labels);
+    student_model.content = Set(dataset_student.content.clone());
+
+    let student = student_model.update(conn).await?;
+    if content_changed || !has_lore {
+        save_lore_chunks(conn, student.id, &student.content).await?;
+    }
+
+    Ok(true)
+}
+
+// 切分档案文本并建立检索索引，覆盖已有片段
+async fn save_lore_chunks<C: ConnectionTrait>(
+    conn: &C,
+    student_id: i32,
+    content: &str,
+) -> Result<(), DbErr> {
+    LoreChunk::delete_many()
+        .filter(lore_chunk::Column::StudentId.eq(student_id))
+        .exec(conn)
+        .await?;
+
+    let chunks: Vec<lore_chunk::ActiveModel> = lore::chunk_content(content)
+        .into_iter()
+        .enumerate()
+        .map(|(position, chunk)| {
+            let (terms, length) = lore::index_terms(&chunk);
+            lore_chunk::ActiveModel {
+                id: Default::default(), // 自动生成ID
+                student_id: Set(student_id),
+                position: Set(position as i32),
+                heading: Set(chunk.heading),
+                content: Set(chunk.content),
+                terms: Set(serde_json::to_string(&terms).unwrap_or_default()),
+                length: Set(length as i32),
+            }
+        })
+        .collect();
+    if chunks.is_empty() {
+        return Ok(());
+    }
+
+    LoreChunk::insert_many(chunks).exec(conn).await?;
+
+    Ok(())
+}
+
+// 从档案文本中解析学生资料，合并结构化资料后保存，已有资料时覆盖
+async fn save_student_profile<C: ConnectionTrait>(
+    conn: &C,
+    student_id: i32,
+    content: &str,
+    structured: &profile::StructuredProfile,
+) -> Result<(), DbErr> {
+    let parsed = profile::parse_profile(content).with_structured(structured);
+
+    let profile = student_profile::ActiveModel {
+        student_id: Set(student_id),
+        full_name: Set(parsed.full_name),
+        school: Set(parsed.school),
+        club: Set(parsed.club),
+        grade: Set(parsed.grade),
+        age: Set(parsed.age),
+        birthday: Set(parsed.birthday),
+        birthday_month: Set(parsed.birthday_month),
+        birthday_day: Set(parsed.birthday_day),
+        height: Set(parsed.height),
+        hobbies: Set(parsed.hobbies),
+        bio: Set(parsed.bio),
+        voice_actor: Set(parsed.voice_actor),
+    };
+
+    StudentProfile::insert(profile)
+        .on_conflict(
+            OnConflict::column(student_profile::Column::StudentId)
+                .update_columns([
+                    student_profile::Column::FullName,
+                    student_profile::Column::School,
+                    student_profile::Column::Club,
+                    student_profile::Column::Grade,
+                    student_profile::Column::Age,
+                    student_profile::Column::Birthday,
+                    student_profile::Column::BirthdayMonth,
+                    student_profile::Column::BirthdayDay,
+                    student_profile::Column::Height,
+                    student_profile::Column::Hobbies,
+                    student_profile::Column::Bio,
+                    student_profile::Column::VoiceActor,
+                ])
+                .to_owned(),
+        )
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
@@ -1,17 +1,33 @@
-use entity::prelude::{Conversation, Message, Student};
-use entity::{conversation, message, student};
+use crate::{avatar, bond, calendar, dataset, delivery, memory, prompt, vector, world_info};
+use entity::prelude::{
+    Anniversary, Conversation, Embedding, LoreChunk, Lorebook, LorebookEntry, Message, Persona,
+    PromptTemplate, Student, StudentBond, StudentMemory, StudentProfile, StudentPromptRevision,
+};
+use entity::{
+    anniversary, conversation, embedding, lore_chunk, lorebook, lorebook_entry, message, persona,
+    prompt_template, student, student_bond, student_memory, student_profile,
+    student_prompt_revision,
+};
 use migration::MigratorTrait;
+use sea_orm::prelude::{DateTimeWithTimeZone, Expr};
+use sea_orm::sea_query::{OnConflict, SelectStatement};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait,
-    PaginatorTrait, QueryFilter, QueryOrder, Set,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, FromQueryResult, ModelTrait, QueryFilter, QueryOrder, QueryResult, QuerySelect,
+    QueryTrait, SelectTwo, Set, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 pub type DbClient = Arc<Mutex<DatabaseConnection>>;
 
+// 向量的来源类型
+pub const EMBEDDING_SOURCE_MESSAGE: &str = "message";
+pub const EMBEDDING_SOURCE_MEMORY: &str = "memory";
+
 // 用于序列化和反序列化的结构体
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConversationData {
@@ -23,6 +39,11 @@ pub struct ConversationData {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConversationUpdateData {
     pub title: Option<String>,
+    // 传入标题时是否锁定，为空时锁定非空的标题，自动生成的标题传入false
+    #[serde(default)]
+    pub title_locked: Option<bool>,
+    // 对话级别的补充设定，传入空字符串表示清除
+    pub prompt_override: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,11 +53,168 @@ pub struct StudentWithConversation {
     pub conversation: Option<conversation::Model>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StudentWithProfile {
+    #[serde(flatten)]
+    pub student: student::Model,
+    pub profile: Option<student_profile::Model>,
+}
+
+// 自定义角色相关结构体
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomStudentData {
+    pub name: String,
+    pub avatars: Vec<String>,
+    pub prompt: String,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomStudentUpdateData {
+    pub name: Option<String>,
+    pub avatars: Option<Vec<String>>,
+    pub prompt: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+// prompt模板相关结构体
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromptTemplateData {
+    pub name: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromptTemplateUpdateData {
+    pub name: Option<String>,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonaData {
+    pub name: String,
+    pub address: String,
+    pub bio: Option<String>,
+    pub preferences: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonaUpdateData {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    // 传入空字符串表示清除
+    pub bio: Option<String>,
+    pub preferences: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryData {
+    pub content: String,
+    pub importance: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryUpdateData {
+    pub content: Option<String>,
+    pub importance: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LorebookData {
+    pub name: String,
+    pub scope: String,
+    pub school: Option<String>,
+    pub student_id: Option<i32>,
+    pub scan_depth: Option<i32>,
+    pub token_budget: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LorebookUpdateData {
+    pub name: Option<String>,
+    pub scope: Option<String>,
+    pub school: Option<String>,
+    pub student_id: Option<i32>,
+    pub scan_depth: Option<i32>,
+    pub token_budget: Option<i32>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LorebookEntryData {
+    pub title: Option<String>,
+    pub keywords: Vec<String>,
+    pub use_regex: Option<bool>,
+    pub content: String,
+    pub position: Option<String>,
+    pub priority: Option<i32>,
+    pub constant: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LorebookEntryUpdateData {
+    pub title: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    pub use_regex: Option<bool>,
+    pub content: Option<String>,
+    pub position: Option<String>,
+    pub priority: Option<i32>,
+    pub constant: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnniversaryData {
+    pub student_id: Option<i32>,
+    pub title: String,
+    pub month: i32,
+    pub day: i32,
+    pub year: Option<i32>,
+    pub prompt: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnniversaryUpdateData {
+    pub student_id: Option<i32>,
+    pub title: Option<String>,
+    pub month: Option<i32>,
+    pub day: Option<i32>,
+    // 传入0表示清除
+    pub year: Option<i32>,
+    // 传入空字符串表示清除
+    pub prompt: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConversationWithStudent {
     #[serde(flatten)]
     pub conversation: conversation::Model,
     pub student: student::Model,
+    // 最后一条消息的预览，用于聊天列表展示
+    pub last_message_content: Option<String>,
+    pub last_message_role: Option<String>,
+    pub last_message_at: Option<DateTimeWithTimeZone>,
+    pub unread_count: i64,
+}
+
+impl ConversationWithStudent {
+    // 从对话列表查询的结果行构造，学生不存在的脏数据返回None
+    fn from_row(row: &QueryResult) -> Result<Option<Self>, DbErr> {
+        let conversation = conversation::Model::from_query_result(row, "A_")?;
+        let Some(student) = student::Model::from_query_result_optional(row, "B_")? else {
+            println!("对话 {} 关联的学生不存在，已跳过", conversation.id);
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            conversation,
+            student,
+            last_message_content: row.try_get("", "last_message_content")?,
+            last_message_role: row.try_get("", "last_message_role")?,
+            last_message_at: row.try_get("", "last_message_at")?,
+            unread_count: row.try_get("", "unread_count")?,
+        }))
+    }
 }
 
 // 消息相关结构体
@@ -47,14 +225,52 @@ pub struct MessageData {
     pub content: String,
     pub name: Option<String>,
     pub index: Option<i32>,
+    // 生成该消息时使用的prompt版本，仅助手消息需要
+    #[serde(default)]
+    pub prompt_revision_id: Option<i32>,
+    // 是否为学生主动发送的消息
+    #[serde(default)]
+    pub proactive: bool,
+    // 同一次回复拆分出的多条消息共用的轮次ID
+    #[serde(default)]
+    pub turn_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessagePage {
+    pub messages: Vec<message::Model>,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MessageUpdateData {
+    pub content: Option<String>,
+    pub name: Option<String>,
+}
+
+// 启动状态，数据库或数据集出现问题时应用以降级模式运行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupStatus {
+    pub degraded: bool,
+    pub errors: Vec<String>,
+    pub dataset: Option<dataset::DatasetSyncReport>,
+}
+
+impl StartupStatus {
+    pub fn degraded(error: String) -> Self {
+        Self {
+            degraded: true,
+            errors: vec![error],
+            dataset: None,
+        }
+    }
 }
 
 // 初始化数据库客户端
 pub async fn init_db(
     db_path: PathBuf,
-    students_content_path: PathBuf,
-    students_data_path: PathBuf,
-) -> Result<DbClient, DbErr> {
+    dataset_paths: &dataset::DatasetPaths,
+) -> Result<(DbClient, StartupStatus), DbErr> {
     // 使用应用数据目录而不是相对路径
     // let app_dir =
     //     std::env::current_dir().map_err(|e| DbErr::Custom(format!("无法获取当前目录: {}", e)))?;
@@ -81,331 +297,2072 @@ pub async fn init_db(
     // 运行迁移以确保表结构存在
     migration::Migrator::up(&conn, None).await?;
 
-    // 检查是否需要初始化数据
-    let student_count = Student::find().all(&conn).await?.len();
+    // 逐条送达不会跨越重启，上次退出时未送达的回复直接视为已送达
+    Message::update_many()
+        .col_expr(
+            message::Column::DeliveredAt,
+            Expr::col(message::Column::CreatedAt).into(),
+        )
+        .filter(message::Column::Role.eq("assistant"))
+        .filter(message::Column::DeliveredAt.is_null())
+        .exec(&conn)
+        .await?;
+
+    // 导入或增量更新内置的学生数据集，失败时以降级模式启动，保留已有数据
+    let status = match dataset::sync_students(&conn, dataset_paths).await {
+        Ok(report) => StartupStatus {
+            degraded: false,
+            errors: Vec::new(),
+            dataset: Some(report),
+        },
+        Err(e) => {
+            println!("[ERROR] {}", e);
+            StartupStatus::degraded(e.to_string())
+        }
+    };
 
-    if student_count == 0 {
-        // 数据库中没有学生数据，需要初始化
-        initialize_database(&conn, students_content_path, students_data_path).await?;
-    }
+    ensure_default_prompt_template(&conn).await?;
 
-    Ok(Arc::new(Mutex::new(conn)))
+    Ok((Arc::new(Mutex::new(conn)), status))
 }
 
-// 初始化数据库数据
-async fn initialize_database(
-    conn: &DatabaseConnection,
-    students_content_path: PathBuf,
-    students_data_path: PathBuf,
-) -> Result<(), DbErr> {
-    println!("开始初始化数据库...");
-
-    // 获取学生数据
-    println!("正在获取学生数据...");
-    // let base_url = "https://arona.hanasaki.tech/api/student";
-
-    // let response = reqwest::get(base_url)
-    //     .await
-    //     .map_err(|e| DbErr::Custom(format!("Failed to fetch student data: {}", e)))?;
-
-    // let data: serde_json::Value = response
-    //     .json()
-    //     .await
-    //     .map_err(|e| DbErr::Custom(format!("Failed to parse student data: {}", e)))?;
-    let file = std::fs::File::open(&students_data_path).unwrap();
-    let student_data: serde_json::Value = serde_json::from_reader(file).unwrap();
-
-    let file = std::fs::File::open(&students_content_path).unwrap();
-    let student_content: serde_json::Value = serde_json::from_reader(file).unwrap();
-
-    // 学生默认prompt模板
-    let get_default_prompt = |student_name: &str| -> String {
-        // 在数组中查找对应名称的学生
-        let empty_vec = Vec::new();
-        let student_info = student_content
-            .as_array()
-            .unwrap_or(&empty_vec)
-            .iter()
-            .find(|s| s.get("name").and_then(|n| n.as_str()) == Some(student_name));
-
-        // 获取学生的content
-        let content = student_info
-            .and_then(|s| s.get("content"))
-            .and_then(|c| c.as_str())
-            .unwrap_or_default();
-        format!(
-            "基于以下检索到的关于{}的信息，请以{}的视角回答问题:\n
-            {}\n
-            请记住以下几点:\n
-            1. 完全沉浸在{}的角色中，使用她的说话方式\n
-            2. 不要提及'根据检索信息'或'作为{}'等提示词\n
-            3. 保持对话的轻松自然，就像玩家真的在和{}对话\n
-            4. 请用中文回答用户的问题，可以适当使用日语的语气词，但对话主体得使用中文回答\n,
-            5. 设定中不存在魔法师，不要使用任何魔法师的设定\n",
-            student_name, student_name, content, student_name, student_name, student_name
-        )
-    };
+// 确保默认prompt模板存在
+async fn ensure_default_prompt_template(conn: &DatabaseConnection) -> Result<(), DbErr> {
+    let existing = PromptTemplate::find()
+        .filter(prompt_template::Column::Name.eq(prompt::DEFAULT_TEMPLATE_NAME))
+        .one(conn)
+        .await?;
 
-    // 导入每个学生并创建对应私聊
-    if let Some(students) = student_data.as_array() {
-        for student_data in students {
-            let student_name = student_data["personalName"]
-                .as_str()
-                .unwrap_or_default()
-                .to_string();
-            let student_id = student_data["id"].as_u64().unwrap_or_default().to_string();
-            println!("正在导入学生 {}...", &student_id);
-            let avatar_url = format!(
-                "https://aronacdn.hanasaki.tech/images/student/icon/{}.webp",
-                student_id
-            );
-
-            // 查找学生是否已存在
-            let existing_student = Student::find()
-                .filter(student::Column::Name.eq(&student_name))
-                .one(conn)
-                .await?;
-
-            if let Some(existing) = existing_student {
-                // 如果学生已存在，更新avatars数组
-                let mut avatars = Vec::new();
-
-                // 尝试解析现有的avatars JSON
-                if let Ok(parsed_avatars) = serde_json::from_str::<Vec<String>>(&existing.avatars) {
-                    avatars = parsed_avatars;
-                } else if !existing.avatars.is_empty() {
-                    // 如果解析失败，说明可能不是JSON格式，将其作为单个元素
-                    avatars.push(existing.avatars.clone());
-                }
-
-                // 检查是否已存在相同的avatar
-                if !avatars.contains(&avatar_url) {
-                    avatars.push(avatar_url);
-                }
-
-                // 更新学生记录
-                let mut student_model: student::ActiveModel = existing.into();
-                student_model.avatars = Set(serde_json::to_string(&avatars).unwrap_or_default());
-
-                student_model.update(conn).await?;
-
-                println!("已更新学生 {} 的头像数组", student_name);
-            } else {
-                // 创建新学生记录，avatars作为JSON数组
-                let avatars = vec![avatar_url];
-                let avatars_json = serde_json::to_string(&avatars).unwrap_or_default();
-
-                let student_model = student::ActiveModel {
-                    id: Default::default(), // 自动生成ID
-                    name: Set(student_name.clone()),
-                    avatars: Set(avatars_json),
-                    prompt: Set(get_default_prompt(&student_name)),
-                };
-
-                let student = student_model.insert(conn).await?;
-
-                // 为每个学生创建对话
-                let uuid = uuid::Uuid::new_v4().to_string();
-                let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
-
-                let conversation = conversation::ActiveModel {
-                    id: Set(uuid),
-                    created_at: Set(now),
-                    updated_at: Set(now),
-                    title: Set(student.name.clone()),
-                    student_name: Set(student.name.clone()),
-                };
-
-                conversation.insert(conn).await?;
-
-                println!("已创建学生 {} 及对应对话", student.name);
-            }
-        }
+    if existing.is_none() {
+        let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+        let template = prompt_template::ActiveModel {
+            id: Default::default(), // 自动生成ID
+            name: Set(prompt::DEFAULT_TEMPLATE_NAME.to_string()),
+            content: Set(prompt::DEFAULT_TEMPLATE.to_string()),
+            created_at: Set(now),
+            updated_at: Set(now),
+        };
+        template.insert(conn).await?;
     }
 
-    println!("数据库初始化完成！");
     Ok(())
 }
 
-// 获取所有对话
-pub async fn get_conversations(client: &DbClient) -> Result<Vec<ConversationWithStudent>, DbErr> {
+// 获取学生及其资料
+pub async fn get_student_profile(
+    client: &DbClient,
+    student_id: i32,
+) -> Result<Option<StudentWithProfile>, DbErr> {
     let conn = client.lock().await;
 
-    let conversations = Conversation::find()
-        .order_by_desc(conversation::Column::UpdatedAt)
-        .all(&*conn)
+    let result = Student::find_by_id(student_id)
+        .find_also_related(StudentProfile)
+        .one(&*conn)
         .await?;
 
-    let mut result = Vec::new();
-
-    for conversation_model in conversations {
-        let student = conversation_model
-            .find_related(Student)
-            .one(&*conn)
-            .await?
-            .expect("Student should exist");
+    Ok(result.map(|(student, profile)| StudentWithProfile { student, profile }))
+}
 
-        result.push(ConversationWithStudent {
-            conversation: conversation_model,
-            student,
-        });
-    }
+// 获取所有学生
+pub async fn get_students(client: &DbClient) -> Result<Vec<student::Model>, DbErr> {
+    let conn = client.lock().await;
 
-    Ok(result)
+    Student::find()
+        .order_by_asc(student::Column::Id)
+        .all(&*conn)
+        .await
 }
 
-// 根据ID获取对话
-pub async fn get_conversation_by_id(
+// 根据ID获取学生
+pub async fn get_student_by_id(
     client: &DbClient,
-    id: String,
-) -> Result<Option<ConversationWithStudent>, DbErr> {
+    id: i32,
+) -> Result<Option<student::Model>, DbErr> {
     let conn = client.lock().await;
 
-    let conversation = Conversation::find_by_id(id).one(&*conn).await?;
-
-    if let Some(conversation_model) = conversation {
-        let student = conversation_model
-            .find_related(Student)
-            .one(&*conn)
-            .await?
-            .expect("Student should exist");
-
-        Ok(Some(ConversationWithStudent {
-            conversation: conversation_model,
-            student,
-        }))
-    } else {
-        Ok(None)
-    }
+    Student::find_by_id(id).one(&*conn).await
 }
 
-// 创建新对话
-pub async fn create_conversation(
+// 创建自定义角色及对应私聊
+pub async fn create_custom_student(
     client: &DbClient,
-    data: ConversationData,
-) -> Result<conversation::Model, DbErr> {
+    data: CustomStudentData,
+) -> Result<student::Model, DbErr> {
     let conn = client.lock().await;
+    let txn = conn.begin().await?;
 
-    let uuid = uuid::Uuid::new_v4().to_string();
+    let student_model = student::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        name: Set(data.name),
+        avatars: Set(serde_json::to_string(&data.avatars).unwrap_or_default()),
+        prompt: Set(data.prompt),
+        is_custom: Set(true),
+        metadata: Set(metadata_to_string(data.metadata)),
+        content: Set(String::new()),
+        prompt_template_id: Set(None),
+        avatar_labels: Set("{}".to_string()),
+        typing_speed: Set(None),
+    };
 
-    let now = chrono::Utc::now().naive_utc();
+    let student = student_model.insert(&txn).await?;
+    create_prompt_revision(&txn, &student, None).await?;
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
 
     let conversation = conversation::ActiveModel {
-        id: Set(uuid),
-        created_at: Set(now.and_utc().fixed_offset()),
-        updated_at: Set(now.and_utc().fixed_offset()),
-        title: Set(data.title.unwrap_or_default()),
-        student_name: Set(data.student_name),
+        id: Set(uuid::Uuid::new_v4().to_string()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        title: Set(student.name.clone()),
+        student_name: Set(student.name.clone()),
+        last_read_index: Set(-1),
+        prompt_override: Set(None),
+        avatar: Set(None),
+        persona_id: Set(None),
+        title_locked: Set(false),
     };
 
-    let result = conversation.insert(&*conn).await?;
-    Ok(result)
+    conversation.insert(&txn).await?;
+    txn.commit().await?;
+
+    Ok(student)
 }
 
-// 更新对话
-pub async fn update_conversation(
+// 更新自定义角色
+pub async fn update_custom_student(
     client: &DbClient,
-    id: String,
-    data: ConversationUpdateData,
-) -> Result<conversation::Model, DbErr> {
+    id: i32,
+    data: CustomStudentUpdateData,
+) -> Result<student::Model, DbErr> {
     let conn = client.lock().await;
 
-    let conversation = Conversation::find_by_id(id.clone())
-        .one(&*conn)
-        .await?
-        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
-
-    let mut conversation: conversation::ActiveModel = conversation.into();
+    let student = find_custom_student(&*conn, id).await?;
+    let prompt_changed = data.prompt.as_ref().is_some_and(|p| *p != student.prompt);
+    let mut student: student::ActiveModel = student.into();
 
-    if let Some(title) = data.title {
-        conversation.title = Set(title);
+    // 对话通过外键级联更新student_name
+    if let Some(name) = data.name {
+        student.name = Set(name);
+    }
+    if let Some(avatars) = data.avatars {
+        student.avatars = Set(serde_json::to_string(&avatars).unwrap_or_default());
+    }
+    if let Some(prompt) = data.prompt {
+        student.prompt = Set(prompt);
+    }
+    if data.metadata.is_some() {
+        student.metadata = Set(metadata_to_string(data.metadata));
     }
 
-    conversation.updated_at = Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset());
+    let txn = conn.begin().await?;
+    let result = student.update(&txn).await?;
+    if prompt_changed {
+        create_prompt_revision(&txn, &result, None).await?;
+    }
+    txn.commit().await?;
 
-    let result = conversation.update(&*conn).await?;
     Ok(result)
 }
 
-// 删除对话
-pub async fn delete_conversation(
+// 修改学生的prompt并记录新版本
+pub async fn update_student_prompt(
     client: &DbClient,
-    id: String,
-) -> Result<conversation::Model, DbErr> {
+    student_id: i32,
+    prompt: String,
+    note: Option<String>,
+) -> Result<student::Model, DbErr> {
     let conn = client.lock().await;
 
-    let conversation = Conversation::find_by_id(id.clone())
+    let student = Student::find_by_id(student_id)
         .one(&*conn)
         .await?
-        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
+        .ok_or_else(|| DbErr::Custom("Student not found".to_string()))?;
 
-    let result = conversation.clone();
-    conversation.delete(&*conn).await?;
+    let txn = conn.begin().await?;
+    let result = set_student_prompt(&txn, student, prompt, note).await?;
+    txn.commit().await?;
 
     Ok(result)
 }
 
-// 获取对话的所有消息
-pub async fn get_messages_by_conversation_id(
+// 获取学生的prompt历史版本，按时间倒序
+pub async fn get_student_prompt_revisions(
     client: &DbClient,
-    conversation_id: String,
-) -> Result<Vec<message::Model>, DbErr> {
+    student_id: i32,
+) -> Result<Vec<student_prompt_revision::Model>, DbErr> {
     let conn = client.lock().await;
 
-    let messages = Message::find()
-        .filter(message::Column::ConversationId.eq(conversation_id))
-        .order_by_asc(message::Column::Index)
+    StudentPromptRevision::find()
+        .filter(student_prompt_revision::Column::StudentId.eq(student_id))
+        .order_by_desc(student_prompt_revision::Column::Id)
         .all(&*conn)
-        .await?;
-
-    Ok(messages)
+        .await
 }
 
-// 获取对话的消息（带分页）
-pub async fn get_messages_by_conversation_id_with_pagination(
+// 根据ID获取prompt历史版本
+pub async fn get_student_prompt_revision_by_id(
     client: &DbClient,
-    conversation_id: String,
-    page: u64,
-    page_size: u64,
-) -> Result<Vec<message::Model>, DbErr> {
+    id: i32,
+) -> Result<Option<student_prompt_revision::Model>, DbErr> {
     let conn = client.lock().await;
 
-    let messages = Message::find()
-        .filter(message::Column::ConversationId.eq(conversation_id))
-        .order_by_asc(message::Column::Index)
-        .paginate(&*conn, page_size)
-        .fetch_page(page)
-        .await?;
+    StudentPromptRevision::find_by_id(id).one(&*conn).await
+}
 
-    Ok(messages)
+// 获取学生当前的prompt版本ID，prompt或使用的模板与最新版本不同时先记录新版本
+//
+// 官方学生的行为主要来自模板，没有修改过prompt时在第一次回复时记录版本
+pub async fn get_current_prompt_revision_id(
+    client: &DbClient,
+    student: &student::Model,
+) -> Result<i32, DbErr> {
+    let conn = client.lock().await;
+
+    sync_prompt_revision(&*conn, student, None).await
 }
 
-// 创建新消息
-pub async fn create_message(client: &DbClient, data: MessageData) -> Result<message::Model, DbErr> {
+// 恢复到指定的prompt版本，恢复操作本身也会记录为新版本
+pub async fn restore_student_prompt_revision(
+    client: &DbClient,
+    revision_id: i32,
+) -> Result<student::Model, DbErr> {
     let conn = client.lock().await;
 
-    // 获取当前对话中最大的索引值
-    let max_index = Message::find()
-        .filter(message::Column::ConversationId.eq(data.conversation_id.clone()))
-        .order_by_desc(message::Column::Index)
+    let revision = StudentPromptRevision::find_by_id(revision_id)
         .one(&*conn)
         .await?
-        .map(|m| m.index)
-        .unwrap_or(-1);
+        .ok_or_else(|| DbErr::Custom("Prompt revision not found".to_string()))?;
 
-    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let student = Student::find_by_id(revision.student_id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Student not found".to_string()))?;
 
-    let message = message::ActiveModel {
+    let note = format!("恢复自版本 #{}", revision.id);
+
+    let txn = conn.begin().await?;
+    let result = set_student_prompt(&txn, student, revision.prompt, Some(note)).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 删除自定义角色，对话、消息与记忆通过外键级联删除，它们的向量需要单独删除
+pub async fn delete_custom_student(client: &DbClient, id: i32) -> Result<student::Model, DbErr> {
+    let conn = client.lock().await;
+    let txn = conn.begin().await?;
+
+    let student = find_custom_student(&txn, id).await?;
+
+    let conversation_ids = Conversation::find()
+        .select_only()
+        .column(conversation::Column::Id)
+        .filter(conversation::Column::StudentName.eq(&student.name))
+        .into_query();
+    let message_ids = Message::find()
+        .select_only()
+        .column(message::Column::Id)
+        .filter(message::Column::ConversationId.in_subquery(conversation_ids))
+        .into_query();
+    delete_embeddings_in(&txn, EMBEDDING_SOURCE_MESSAGE, message_ids).await?;
+
+    let memory_ids = StudentMemory::find()
+        .select_only()
+        .column(student_memory::Column::Id)
+        .filter(student_memory::Column::StudentId.eq(id))
+        .into_query();
+    delete_embeddings_in(&txn, EMBEDDING_SOURCE_MEMORY, memory_ids).await?;
+
+    let result = student.clone();
+    student.delete(&txn).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 获取所有prompt模板
+pub async fn get_prompt_templates(client: &DbClient) -> Result<Vec<prompt_template::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    PromptTemplate::find()
+        .order_by_asc(prompt_template::Column::Id)
+        .all(&*conn)
+        .await
+}
+
+// 创建prompt模板
+pub async fn create_prompt_template(
+    client: &DbClient,
+    data: PromptTemplateData,
+) -> Result<prompt_template::Model, DbErr> {
+    let conn = client.lock().await;
+
+    prompt::validate(&data.content).map_err(|e| DbErr::Custom(e.to_string()))?;
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let template = prompt_template::ActiveModel {
         id: Default::default(), // 自动生成ID
-        conversation_id: Set(data.conversation_id),
-        role: Set(data.role),
+        name: Set(data.name),
         content: Set(data.content),
-        name: Set(data.name.unwrap_or_default()),
         created_at: Set(now),
-        index: Set(data.index.unwrap_or(max_index + 1)),
+        updated_at: Set(now),
     };
 
-    // 更新对话的更新时间
-    let result = message.insert(&*conn).await?;
+    let result = template.insert(&*conn).await?;
+    Ok(result)
+}
+
+// 更新prompt模板
+pub async fn update_prompt_template(
+    client: &DbClient,
+    id: i32,
+    data: PromptTemplateUpdateData,
+) -> Result<prompt_template::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let template = PromptTemplate::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Prompt template not found".to_string()))?;
+
+    let is_default = template.name == prompt::DEFAULT_TEMPLATE_NAME;
+    let mut template: prompt_template::ActiveModel = template.into();
+
+    if let Some(name) = data.name {
+        if is_default && name != prompt::DEFAULT_TEMPLATE_NAME {
+            return Err(DbErr::Custom(
+                "The default prompt template cannot be renamed".to_string(),
+            ));
+        }
+        template.name = Set(name);
+    }
+    if let Some(content) = data.content {
+        prompt::validate(&content).map_err(|e| DbErr::Custom(e.to_string()))?;
+        template.content = Set(content);
+    }
+
+    template.updated_at = Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset());
+
+    // 模板内容变化时为使用它的学生记录新版本
+    let txn = conn.begin().await?;
+    let result = template.update(&txn).await?;
+    let students = students_using_template(&txn, &result).await?;
+    sync_template_revisions(&txn, students, format!("修改prompt模板「{}」", result.name)).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 删除prompt模板，使用该模板的学生回退到默认模板
+pub async fn delete_prompt_template(
+    client: &DbClient,
+    id: i32,
+) -> Result<prompt_template::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let template = PromptTemplate::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Prompt template not found".to_string()))?;
+
+    if template.name == prompt::DEFAULT_TEMPLATE_NAME {
+        return Err(DbErr::Custom(
+            "The default prompt template cannot be deleted".to_string(),
+        ));
+    }
+
+    let txn = conn.begin().await?;
+
+    let student_ids: Vec<i32> = Student::find()
+        .select_only()
+        .column(student::Column::Id)
+        .filter(student::Column::PromptTemplateId.eq(id))
+        .into_tuple()
+        .all(&txn)
+        .await?;
+    Student::update_many()
+        .col_expr(
+            student::Column::PromptTemplateId,
+            Expr::value(Option::<i32>::None),
+        )
+        .filter(student::Column::PromptTemplateId.eq(id))
+        .exec(&txn)
+        .await?;
+
+    let result = template.clone();
+    template.delete(&txn).await?;
+
+    // 回退到默认模板的学生记录新版本
+    let students = Student::find()
+        .filter(student::Column::Id.is_in(student_ids))
+        .all(&txn)
+        .await?;
+    sync_template_revisions(&txn, students, format!("删除prompt模板「{}」", result.name)).await?;
+    txn.commit().await?;
+
     Ok(result)
 }
+
+// 设置学生使用的prompt模板，传入None使用默认模板
+pub async fn set_student_prompt_template(
+    client: &DbClient,
+    student_id: i32,
+    template_id: Option<i32>,
+) -> Result<student::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let student = Student::find_by_id(student_id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Student not found".to_string()))?;
+
+    if let Some(template_id) = template_id {
+        PromptTemplate::find_by_id(template_id)
+            .one(&*conn)
+            .await?
+            .ok_or_else(|| DbErr::Custom("Prompt template not found".to_string()))?;
+    }
+
+    let mut student: student::ActiveModel = student.into();
+    student.prompt_template_id = Set(template_id);
+
+    let txn = conn.begin().await?;
+    let result = student.update(&txn).await?;
+    sync_prompt_revision(&txn, &result, Some("切换prompt模板".to_string())).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 设置学生的打字速度（字/分钟），传入None使用默认速度
+pub async fn set_student_typing_speed(
+    client: &DbClient,
+    student_id: i32,
+    typing_speed: Option<i32>,
+) -> Result<student::Model, DbErr> {
+    let conn = client.lock().await;
+
+    delivery::validate_typing_speed(typing_speed).map_err(DbErr::Custom)?;
+
+    let student = Student::find_by_id(student_id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Student not found".to_string()))?;
+
+    let mut student: student::ActiveModel = student.into();
+    student.typing_speed = Set(typing_speed);
+
+    let result = student.update(&*conn).await?;
+    Ok(result)
+}
+
+// 获取学生实际使用的prompt模板：指定模板不存在时回退到默认模板
+pub async fn get_prompt_template_for_student(
+    client: &DbClient,
+    student: &student::Model,
+) -> Result<prompt_template::Model, DbErr> {
+    let conn = client.lock().await;
+
+    effective_prompt_template(&*conn, student)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Default prompt template not found".to_string()))
+}
+
+// 根据ID获取prompt模板
+pub async fn get_prompt_template_by_id(
+    client: &DbClient,
+    id: i32,
+) -> Result<Option<prompt_template::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    PromptTemplate::find_by_id(id).one(&*conn).await
+}
+
+// 获取与学生的羁绊，尚未互动过时为空
+pub async fn get_student_bond(
+    client: &DbClient,
+    student_id: i32,
+) -> Result<Option<student_bond::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    StudentBond::find_by_id(student_id).one(&*conn).await
+}
+
+// 获取所有学生的羁绊
+pub async fn get_student_bonds(client: &DbClient) -> Result<Vec<student_bond::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    StudentBond::find()
+        .order_by_desc(student_bond::Column::Points)
+        .all(&*conn)
+        .await
+}
+
+// 增加羁绊点数并重新计算等级，返回更新后的羁绊与之前的等级
+pub async fn add_student_bond_points(
+    client: &DbClient,
+    student_id: i32,
+    points: i32,
+) -> Result<(student_bond::Model, i32), DbErr> {
+    let conn = client.lock().await;
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let existing = StudentBond::find_by_id(student_id).one(&*conn).await?;
+    let previous_level = existing.as_ref().map_or(1, |b| b.level);
+
+    let result = match existing {
+        Some(existing) => {
+            let total = (existing.points + points).max(0);
+            let mut bond: student_bond::ActiveModel = existing.into();
+            bond.points = Set(total);
+            bond.level = Set(bond::level_for_points(total));
+            bond.updated_at = Set(now);
+            bond.update(&*conn).await?
+        }
+        None => {
+            let total = points.max(0);
+            let bond = student_bond::ActiveModel {
+                student_id: Set(student_id),
+                points: Set(total),
+                level: Set(bond::level_for_points(total)),
+                updated_at: Set(now),
+            };
+            bond.insert(&*conn).await?
+        }
+    };
+
+    Ok((result, previous_level))
+}
+
+// 获取学生的背景资料片段，按原文顺序排列
+pub async fn get_lore_chunks(
+    client: &DbClient,
+    student_id: i32,
+) -> Result<Vec<lore_chunk::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    LoreChunk::find()
+        .filter(lore_chunk::Column::StudentId.eq(student_id))
+        .order_by_asc(lore_chunk::Column::Position)
+        .all(&*conn)
+        .await
+}
+
+// 获取学生的所有记忆，按重要程度排序
+pub async fn get_student_memories(
+    client: &DbClient,
+    student_id: i32,
+) -> Result<Vec<student_memory::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    StudentMemory::find()
+        .filter(student_memory::Column::StudentId.eq(student_id))
+        .order_by_desc(student_memory::Column::Importance)
+        .order_by_desc(student_memory::Column::UpdatedAt)
+        .all(&*conn)
+        .await
+}
+
+// 手动添加记忆
+pub async fn create_student_memory(
+    client: &DbClient,
+    student_id: i32,
+    data: MemoryData,
+) -> Result<student_memory::Model, DbErr> {
+    let conn = client.lock().await;
+
+    Student::find_by_id(student_id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Student not found".to_string()))?;
+
+    insert_memory(
+        &*conn,
+        student_id,
+        data.content,
+        data.importance.unwrap_or(memory::MIN_IMPORTANCE),
+    )
+    .await
+}
+
+// 保存从对话中提取的记忆，跳过与已有记忆重复的内容
+pub async fn save_extracted_memories(
+    client: &DbClient,
+    student_id: i32,
+    facts: Vec<memory::ExtractedFact>,
+) -> Result<Vec<student_memory::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    let mut existing = StudentMemory::find()
+        .filter(student_memory::Column::StudentId.eq(student_id))
+        .all(&*conn)
+        .await?;
+
+    let txn = conn.begin().await?;
+
+    let mut saved = Vec::new();
+    for fact in facts {
+        if memory::is_duplicate(&fact.content, &existing) {
+            continue;
+        }
+        let model = insert_memory(&txn, student_id, fact.content, fact.importance).await?;
+        existing.push(model.clone());
+        saved.push(model);
+    }
+
+    txn.commit().await?;
+
+    Ok(saved)
+}
+
+// 编辑记忆
+pub async fn update_student_memory(
+    client: &DbClient,
+    id: i32,
+    data: MemoryUpdateData,
+) -> Result<student_memory::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let memory = StudentMemory::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Memory not found".to_string()))?;
+
+    let mut memory_model: student_memory::ActiveModel = memory.into();
+
+    if let Some(content) = data.content {
+        if content.trim().is_empty() {
+            return Err(DbErr::Custom("Memory content cannot be empty".to_string()));
+        }
+        memory_model.content = Set(content.trim().to_string());
+        // 内容变化后原有向量失效
+        delete_embedding(&*conn, EMBEDDING_SOURCE_MEMORY, id).await?;
+    }
+    if let Some(importance) = data.importance {
+        memory_model.importance =
+            Set(importance.clamp(memory::MIN_IMPORTANCE, memory::MAX_IMPORTANCE));
+    }
+
+    memory_model.updated_at = Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset());
+
+    let result = memory_model.update(&*conn).await?;
+    Ok(result)
+}
+
+// 删除记忆
+pub async fn delete_student_memory(
+    client: &DbClient,
+    id: i32,
+) -> Result<student_memory::Model, DbErr> {
+    let conn = client.lock().await;
+    let txn = conn.begin().await?;
+
+    let memory = StudentMemory::find_by_id(id)
+        .one(&txn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Memory not found".to_string()))?;
+
+    let result = memory.clone();
+    memory.delete(&txn).await?;
+    delete_embedding(&txn, EMBEDDING_SOURCE_MEMORY, id).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 记录记忆被注入prompt的时间
+pub async fn mark_memories_used(client: &DbClient, ids: Vec<i32>) -> Result<(), DbErr> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let conn = client.lock().await;
+
+    StudentMemory::update_many()
+        .col_expr(
+            student_memory::Column::LastUsedAt,
+            Expr::value(chrono::Utc::now().naive_utc().and_utc().fixed_offset()),
+        )
+        .filter(student_memory::Column::Id.is_in(ids))
+        .exec(&*conn)
+        .await?;
+
+    Ok(())
+}
+
+// 获取所有世界书
+pub async fn get_lorebooks(client: &DbClient) -> Result<Vec<lorebook::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    Lorebook::find()
+        .order_by_asc(lorebook::Column::Id)
+        .all(&*conn)
+        .await
+}
+
+// 创建世界书
+pub async fn create_lorebook(
+    client: &DbClient,
+    data: LorebookData,
+) -> Result<lorebook::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let school = data
+        .school
+        .filter(|_| data.scope == world_info::SCOPE_SCHOOL);
+    let student_id = data
+        .student_id
+        .filter(|_| data.scope == world_info::SCOPE_STUDENT);
+    world_info::validate_scope(&data.scope, school.as_deref(), student_id)
+        .map_err(DbErr::Custom)?;
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let lorebook = lorebook::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        name: Set(data.name),
+        scope: Set(data.scope),
+        school: Set(school.map(|s| s.trim().to_string())),
+        student_id: Set(student_id),
+        scan_depth: Set(data
+            .scan_depth
+            .unwrap_or(world_info::DEFAULT_SCAN_DEPTH)
+            .max(0)),
+        token_budget: Set(data
+            .token_budget
+            .unwrap_or(world_info::DEFAULT_TOKEN_BUDGET)
+            .max(0)),
+        enabled: Set(true),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let result = lorebook.insert(&*conn).await?;
+    Ok(result)
+}
+
+// 更新世界书，修改作用范围时同时更新对应的学校或学生
+pub async fn update_lorebook(
+    client: &DbClient,
+    id: i32,
+    data: LorebookUpdateData,
+) -> Result<lorebook::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let existing = Lorebook::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Lorebook not found".to_string()))?;
+
+    let scope = data.scope.unwrap_or(existing.scope.clone());
+    let school = data
+        .school
+        .or(existing.school.clone())
+        .filter(|_| scope == world_info::SCOPE_SCHOOL);
+    let student_id = data
+        .student_id
+        .or(existing.student_id)
+        .filter(|_| scope == world_info::SCOPE_STUDENT);
+    world_info::validate_scope(&scope, school.as_deref(), student_id).map_err(DbErr::Custom)?;
+
+    let mut lorebook: lorebook::ActiveModel = existing.into();
+
+    if let Some(name) = data.name {
+        lorebook.name = Set(name);
+    }
+    lorebook.scope = Set(scope);
+    lorebook.school = Set(school.map(|s| s.trim().to_string()));
+    lorebook.student_id = Set(student_id);
+    if let Some(scan_depth) = data.scan_depth {
+        lorebook.scan_depth = Set(scan_depth.max(0));
+    }
+    if let Some(token_budget) = data.token_budget {
+        lorebook.token_budget = Set(token_budget.max(0));
+    }
+    if let Some(enabled) = data.enabled {
+        lorebook.enabled = Set(enabled);
+    }
+
+    lorebook.updated_at = Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset());
+
+    let result = lorebook.update(&*conn).await?;
+    Ok(result)
+}
+
+// 删除世界书，词条通过外键级联删除
+pub async fn delete_lorebook(client: &DbClient, id: i32) -> Result<lorebook::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let lorebook = Lorebook::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Lorebook not found".to_string()))?;
+
+    let result = lorebook.clone();
+    lorebook.delete(&*conn).await?;
+
+    Ok(result)
+}
+
+// 获取世界书的所有词条，按优先级排序
+pub async fn get_lorebook_entries(
+    client: &DbClient,
+    lorebook_id: i32,
+) -> Result<Vec<lorebook_entry::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    LorebookEntry::find()
+        .filter(lorebook_entry::Column::LorebookId.eq(lorebook_id))
+        .order_by_desc(lorebook_entry::Column::Priority)
+        .order_by_asc(lorebook_entry::Column::Id)
+        .all(&*conn)
+        .await
+}
+
+// 创建世界书词条
+pub async fn create_lorebook_entry(
+    client: &DbClient,
+    lorebook_id: i32,
+    data: LorebookEntryData,
+) -> Result<lorebook_entry::Model, DbErr> {
+    let conn = client.lock().await;
+
+    Lorebook::find_by_id(lorebook_id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Lorebook not found".to_string()))?;
+
+    let use_regex = data.use_regex.unwrap_or(false);
+    let position = data
+        .position
+        .unwrap_or_else(|| world_info::POSITION_AFTER.to_string());
+    world_info::validate_entry(&data.keywords, use_regex, &position).map_err(DbErr::Custom)?;
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let entry = lorebook_entry::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        lorebook_id: Set(lorebook_id),
+        title: Set(data.title.unwrap_or_default()),
+        keywords: Set(serde_json::to_string(&data.keywords).unwrap_or_default()),
+        use_regex: Set(use_regex),
+        content: Set(data.content),
+        position: Set(position),
+        priority: Set(data.priority.unwrap_or(0)),
+        constant: Set(data.constant.unwrap_or(false)),
+        enabled: Set(true),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let result = entry.insert(&*conn).await?;
+    Ok(result)
+}
+
+// 更新世界书词条
+pub async fn update_lorebook_entry(
+    client: &DbClient,
+    id: i32,
+    data: LorebookEntryUpdateData,
+) -> Result<lorebook_entry::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let existing = LorebookEntry::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Lorebook entry not found".to_string()))?;
+
+    let keywords = data
+        .keywords
+        .unwrap_or_else(|| world_info::parse_keywords(&existing.keywords));
+    let use_regex = data.use_regex.unwrap_or(existing.use_regex);
+    let position = data.position.unwrap_or(existing.position.clone());
+    world_info::validate_entry(&keywords, use_regex, &position).map_err(DbErr::Custom)?;
+
+    let mut entry: lorebook_entry::ActiveModel = existing.into();
+
+    if let Some(title) = data.title {
+        entry.title = Set(title);
+    }
+    entry.keywords = Set(serde_json::to_string(&keywords).unwrap_or_default());
+    entry.use_regex = Set(use_regex);
+    if let Some(content) = data.content {
+        entry.content = Set(content);
+    }
+    entry.position = Set(position);
+    if let Some(priority) = data.priority {
+        entry.priority = Set(priority);
+    }
+    if let Some(constant) = data.constant {
+        entry.constant = Set(constant);
+    }
+    if let Some(enabled) = data.enabled {
+        entry.enabled = Set(enabled);
+    }
+
+    entry.updated_at = Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset());
+
+    let result = entry.update(&*conn).await?;
+    Ok(result)
+}
+
+// 删除世界书词条
+pub async fn delete_lorebook_entry(
+    client: &DbClient,
+    id: i32,
+) -> Result<lorebook_entry::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let entry = LorebookEntry::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Lorebook entry not found".to_string()))?;
+
+    let result = entry.clone();
+    entry.delete(&*conn).await?;
+
+    Ok(result)
+}
+
+// 获取作用于指定学生的已启用世界书及其词条
+pub async fn get_lorebooks_for_student(
+    client: &DbClient,
+    student_id: i32,
+) -> Result<Vec<(lorebook::Model, Vec<lorebook_entry::Model>)>, DbErr> {
+    let conn = client.lock().await;
+
+    let school = StudentProfile::find_by_id(student_id)
+        .one(&*conn)
+        .await?
+        .and_then(|p| p.school);
+
+    let lorebooks = Lorebook::find()
+        .filter(lorebook::Column::Enabled.eq(true))
+        .order_by_asc(lorebook::Column::Id)
+        .find_with_related(LorebookEntry)
+        .all(&*conn)
+        .await?;
+
+    Ok(lorebooks
+        .into_iter()
+        .filter(|(book, _)| world_info::applies_to(book, student_id, school.as_deref()))
+        .collect())
+}
+
+// 获取所有自定义纪念日
+pub async fn get_anniversaries(client: &DbClient) -> Result<Vec<anniversary::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    Anniversary::find()
+        .order_by_asc(anniversary::Column::Month)
+        .order_by_asc(anniversary::Column::Day)
+        .all(&*conn)
+        .await
+}
+
+// 创建自定义纪念日
+pub async fn create_anniversary(
+    client: &DbClient,
+    data: AnniversaryData,
+) -> Result<anniversary::Model, DbErr> {
+    let conn = client.lock().await;
+
+    calendar::validate_anniversary(&data.title, data.month, data.day).map_err(DbErr::Custom)?;
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let anniversary = anniversary::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        student_id: Set(data.student_id),
+        title: Set(data.title.trim().to_string()),
+        month: Set(data.month),
+        day: Set(data.day),
+        year: Set(data.year.filter(|y| *y > 0)),
+        prompt: Set(data.prompt.filter(|p| !p.trim().is_empty())),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let result = anniversary.insert(&*conn).await?;
+    Ok(result)
+}
+
+// 更新自定义纪念日
+pub async fn update_anniversary(
+    client: &DbClient,
+    id: i32,
+    data: AnniversaryUpdateData,
+) -> Result<anniversary::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let existing = Anniversary::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Anniversary not found".to_string()))?;
+
+    let title = data.title.unwrap_or(existing.title.clone());
+    let month = data.month.unwrap_or(existing.month);
+    let day = data.day.unwrap_or(existing.day);
+    calendar::validate_anniversary(&title, month, day).map_err(DbErr::Custom)?;
+
+    let mut anniversary: anniversary::ActiveModel = existing.into();
+
+    if let Some(student_id) = data.student_id {
+        anniversary.student_id = Set(Some(student_id));
+    }
+    anniversary.title = Set(title.trim().to_string());
+    anniversary.month = Set(month);
+    anniversary.day = Set(day);
+    if let Some(year) = data.year {
+        anniversary.year = Set(Some(year).filter(|y| *y > 0));
+    }
+    if let Some(prompt) = data.prompt {
+        anniversary.prompt = Set(Some(prompt).filter(|p| !p.trim().is_empty()));
+    }
+
+    anniversary.updated_at = Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset());
+
+    let result = anniversary.update(&*conn).await?;
+    Ok(result)
+}
+
+// 删除自定义纪念日
+pub async fn delete_anniversary(client: &DbClient, id: i32) -> Result<anniversary::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let anniversary = Anniversary::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Anniversary not found".to_string()))?;
+
+    let result = anniversary.clone();
+    anniversary.delete(&*conn).await?;
+
+    Ok(result)
+}
+
+// 收集日历中每年重复的日子：学生生日、第一次聊天的纪念日与自定义纪念日
+//
+// 内置学生的对话在导入数据集时就已创建，因此第一次聊天的日期取对话中第一条用户消息的时间，
+// 没有聊过天的学生不计入
+pub async fn get_calendar_sources(client: &DbClient) -> Result<Vec<calendar::EventSource>, DbErr> {
+    let conn = client.lock().await;
+
+    let students = Student::find()
+        .find_also_related(StudentProfile)
+        .order_by_asc(student::Column::Id)
+        .all(&*conn)
+        .await?;
+
+    // 预置的对话在安装时一同创建，创建时间都相同，因此以用户的第一条消息作为第一次聊天的时间
+    let first_chats: HashMap<String, DateTimeWithTimeZone> = Conversation::find()
+        .select_only()
+        .column(conversation::Column::StudentName)
+        .expr_as(
+            Expr::cust(
+                "(SELECT MIN(m.created_at) FROM message m \
+                 WHERE m.conversation_id = conversation.id AND m.role = 'user')",
+            ),
+            "first_chat_at",
+        )
+        .into_tuple::<(String, Option<DateTimeWithTimeZone>)>()
+        .all(&*conn)
+        .await?
+        .into_iter()
+        .filter_map(|(name, first_chat_at)| Some((name, first_chat_at?)))
+        .collect();
+
+    let anniversaries = Anniversary::find()
+        .order_by_asc(anniversary::Column::Id)
+        .all(&*conn)
+        .await?;
+
+    let mut sources = Vec::new();
+    for (student, profile) in &students {
+        let birthday = profile.as_ref().and_then(|p| {
+            Some((
+                u32::try_from(p.birthday_month?).ok()?,
+                u32::try_from(p.birthday_day?).ok()?,
+            ))
+        });
+        if let Some((month, day)) = birthday {
+            sources.push(calendar::EventSource::birthday(
+                student.id,
+                &student.name,
+                month,
+                day,
+            ));
+        }
+        if let Some(first_chat_at) = first_chats.get(&student.name) {
+            sources.push(calendar::EventSource::first_chat(
+                student.id,
+                &student.name,
+                *first_chat_at,
+            ));
+        }
+    }
+
+    sources.extend(anniversaries.iter().filter_map(|a| {
+        let student_name = a.student_id.and_then(|id| {
+            students
+                .iter()
+                .find(|(student, _)| student.id == id)
+                .map(|(student, _)| student.name.clone())
+        });
+        calendar::EventSource::anniversary(a, student_name)
+    }));
+
+    Ok(sources)
+}
+
+// 保存消息或记忆的向量，已存在时覆盖
+pub async fn save_embedding(
+    client: &DbClient,
+    source_type: &str,
+    source_id: i32,
+    model: &str,
+    values: &[f32],
+) -> Result<(), DbErr> {
+    let conn = client.lock().await;
+
+    let embedding_model = embedding::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        source_type: Set(source_type.to_string()),
+        source_id: Set(source_id),
+        model: Set(model.to_string()),
+        vector: Set(vector::to_blob(values)),
+        created_at: Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset()),
+    };
+
+    Embedding::insert(embedding_model)
+        .on_conflict(
+            OnConflict::columns([embedding::Column::SourceType, embedding::Column::SourceId])
+                .update_columns([
+                    embedding::Column::Model,
+                    embedding::Column::Vector,
+                    embedding::Column::CreatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(&*conn)
+        .await?;
+
+    Ok(())
+}
+
+// 获取指定来源中已有同一模型向量的来源ID
+pub async fn get_embedded_source_ids(
+    client: &DbClient,
+    source_type: &str,
+    source_ids: Vec<i32>,
+    model: &str,
+) -> Result<Vec<i32>, DbErr> {
+    if source_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = client.lock().await;
+
+    Embedding::find()
+        .select_only()
+        .column(embedding::Column::SourceId)
+        .filter(embedding::Column::SourceType.eq(source_type))
+        .filter(embedding::Column::SourceId.is_in(source_ids))
+        .filter(embedding::Column::Model.eq(model))
+        .into_tuple()
+        .all(&*conn)
+        .await
+}
+
+// 获取指定来源由同一模型生成的向量，返回(来源ID, 向量)
+pub async fn get_embeddings(
+    client: &DbClient,
+    source_type: &str,
+    source_ids: Vec<i32>,
+    model: &str,
+) -> Result<Vec<(i32, Vec<f32>)>, DbErr> {
+    if source_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = client.lock().await;
+
+    let embeddings = Embedding::find()
+        .filter(embedding::Column::SourceType.eq(source_type))
+        .filter(embedding::Column::SourceId.is_in(source_ids))
+        .filter(embedding::Column::Model.eq(model))
+        .all(&*conn)
+        .await?;
+
+    Ok(embeddings
+        .into_iter()
+        .map(|e| (e.source_id, vector::from_blob(&e.vector)))
+        .collect())
+}
+
+// 获取所有用户人设
+pub async fn get_personas(client: &DbClient) -> Result<Vec<persona::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    Persona::find()
+        .order_by_asc(persona::Column::Id)
+        .all(&*conn)
+        .await
+}
+
+// 获取启用的用户人设
+pub async fn get_active_persona(client: &DbClient) -> Result<Option<persona::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    Persona::find()
+        .filter(persona::Column::IsActive.eq(true))
+        .one(&*conn)
+        .await
+}
+
+// 获取对话使用的人设，对话未指定时使用启用的人设
+pub async fn get_persona_for_conversation(
+    client: &DbClient,
+    conversation: &conversation::Model,
+) -> Result<Option<persona::Model>, DbErr> {
+    if let Some(persona_id) = conversation.persona_id {
+        let conn = client.lock().await;
+        if let Some(persona) = Persona::find_by_id(persona_id).one(&*conn).await? {
+            return Ok(Some(persona));
+        }
+    }
+
+    get_active_persona(client).await
+}
+
+// 创建用户人设，第一个人设自动启用
+pub async fn create_persona(client: &DbClient, data: PersonaData) -> Result<persona::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let has_persona = Persona::find().one(&*conn).await?.is_some();
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let persona = persona::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        name: Set(data.name),
+        address: Set(data.address),
+        bio: Set(data.bio.filter(|b| !b.is_empty())),
+        preferences: Set(data.preferences.filter(|p| !p.is_empty())),
+        is_active: Set(!has_persona),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    let result = persona.insert(&*conn).await?;
+    Ok(result)
+}
+
+// 更新用户人设
+pub async fn update_persona(
+    client: &DbClient,
+    id: i32,
+    data: PersonaUpdateData,
+) -> Result<persona::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let persona = Persona::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Persona not found".to_string()))?;
+
+    let mut persona: persona::ActiveModel = persona.into();
+
+    if let Some(name) = data.name {
+        persona.name = Set(name);
+    }
+    if let Some(address) = data.address {
+        persona.address = Set(address);
+    }
+    if let Some(bio) = data.bio {
+        persona.bio = Set(Some(bio).filter(|b| !b.is_empty()));
+    }
+    if let Some(preferences) = data.preferences {
+        persona.preferences = Set(Some(preferences).filter(|p| !p.is_empty()));
+    }
+
+    persona.updated_at = Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset());
+
+    let result = persona.update(&*conn).await?;
+    Ok(result)
+}
+
+// 删除用户人设，使用该人设的对话回退到启用的人设
+pub async fn delete_persona(client: &DbClient, id: i32) -> Result<persona::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let persona = Persona::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Persona not found".to_string()))?;
+
+    let txn = conn.begin().await?;
+
+    Conversation::update_many()
+        .col_expr(
+            conversation::Column::PersonaId,
+            Expr::value(Option::<i32>::None),
+        )
+        .filter(conversation::Column::PersonaId.eq(id))
+        .exec(&txn)
+        .await?;
+
+    let result = persona.clone();
+    persona.delete(&txn).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 启用指定的用户人设，传入None时不启用任何人设
+pub async fn set_active_persona(
+    client: &DbClient,
+    id: Option<i32>,
+) -> Result<Option<persona::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    let persona = match id {
+        Some(id) => Some(
+            Persona::find_by_id(id)
+                .one(&*conn)
+                .await?
+                .ok_or_else(|| DbErr::Custom("Persona not found".to_string()))?,
+        ),
+        None => None,
+    };
+
+    let txn = conn.begin().await?;
+
+    Persona::update_many()
+        .col_expr(persona::Column::IsActive, Expr::value(false))
+        .filter(persona::Column::IsActive.eq(true))
+        .exec(&txn)
+        .await?;
+
+    let result = match persona {
+        Some(persona) => {
+            let mut persona: persona::ActiveModel = persona.into();
+            persona.is_active = Set(true);
+            Some(persona.update(&txn).await?)
+        }
+        None => None,
+    };
+
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 获取所有对话
+pub async fn get_conversations(client: &DbClient) -> Result<Vec<ConversationWithStudent>, DbErr> {
+    let conn = client.lock().await;
+
+    let stmt = conversations_with_student_query()
+        .order_by_desc(conversation::Column::UpdatedAt)
+        .build(conn.get_database_backend());
+    let rows = conn.query_all(stmt).await?;
+
+    let mut result = Vec::with_capacity(rows.len());
+    for row in rows {
+        if let Some(conversation) = ConversationWithStudent::from_row(&row)? {
+            result.push(conversation);
+        }
+    }
+
+    Ok(result)
+}
+
+// 根据ID获取对话
+pub async fn get_conversation_by_id(
+    client: &DbClient,
+    id: String,
+) -> Result<Option<ConversationWithStudent>, DbErr> {
+    let conn = client.lock().await;
+
+    let stmt = conversations_with_student_query()
+        .filter(conversation::Column::Id.eq(id))
+        .build(conn.get_database_backend());
+
+    match conn.query_one(stmt).await? {
+        Some(row) => ConversationWithStudent::from_row(&row),
+        None => Ok(None),
+    }
+}
+
+// 设置对话中使用的头像，为空时恢复为默认头像
+pub async fn set_conversation_avatar(
+    client: &DbClient,
+    id: String,
+    avatar: Option<String>,
+) -> Result<conversation::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let conversation = Conversation::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
+
+    if let Some(avatar) = &avatar {
+        let student = Student::find()
+            .filter(student::Column::Name.eq(&conversation.student_name))
+            .one(&*conn)
+            .await?
+            .ok_or_else(|| DbErr::Custom("Student not found".to_string()))?;
+        if !avatar::parse_avatars(&student.avatars).contains(avatar) {
+            return Err(DbErr::Custom("Avatar not found".to_string()));
+        }
+    }
+
+    let mut conversation: conversation::ActiveModel = conversation.into();
+    conversation.avatar = Set(avatar);
+    conversation.update(&*conn).await
+}
+
+// 设置对话使用的人设，为空时使用启用的人设
+pub async fn set_conversation_persona(
+    client: &DbClient,
+    id: String,
+    persona_id: Option<i32>,
+) -> Result<conversation::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let conversation = Conversation::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
+
+    if let Some(persona_id) = persona_id {
+        Persona::find_by_id(persona_id)
+            .one(&*conn)
+            .await?
+            .ok_or_else(|| DbErr::Custom("Persona not found".to_string()))?;
+    }
+
+    let mut conversation: conversation::ActiveModel = conversation.into();
+    conversation.persona_id = Set(persona_id);
+    conversation.update(&*conn).await
+}
+
+// 标记对话为已读
+pub async fn mark_conversation_read(
+    client: &DbClient,
+    id: String,
+) -> Result<conversation::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let conversation = Conversation::find_by_id(id.clone())
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
+
+    // 还在输入中的回复不算已读
+    let max_index = Message::find()
+        .filter(message::Column::ConversationId.eq(id))
+        .filter(delivered_condition())
+        .order_by_desc(message::Column::Index)
+        .one(&*conn)
+        .await?
+        .map(|m| m.index)
+        .unwrap_or(-1);
+
+    // 已读位置只前进不后退
+    if max_index <= conversation.last_read_index {
+        return Ok(conversation);
+    }
+
+    let mut conversation: conversation::ActiveModel = conversation.into();
+    conversation.last_read_index = Set(max_index);
+
+    let result = conversation.update(&*conn).await?;
+    Ok(result)
+}
+
+// 创建新对话
+pub async fn create_conversation(
+    client: &DbClient,
+    data: ConversationData,
+) -> Result<conversation::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let uuid = uuid::Uuid::new_v4().to_string();
+
+    let now = chrono::Utc::now().naive_utc();
+
+    let conversation = conversation::ActiveModel {
+        id: Set(uuid),
+        created_at: Set(now.and_utc().fixed_offset()),
+        updated_at: Set(now.and_utc().fixed_offset()),
+        title: Set(data.title.unwrap_or_default()),
+        student_name: Set(data.student_name),
+        last_read_index: Set(-1),
+        prompt_override: Set(None),
+        avatar: Set(None),
+        persona_id: Set(None),
+        title_locked: Set(false),
+    };
+
+    let result = conversation.insert(&*conn).await?;
+    Ok(result)
+}
+
+// 更新对话
+pub async fn update_conversation(
+    client: &DbClient,
+    id: String,
+    data: ConversationUpdateData,
+) -> Result<conversation::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let conversation = Conversation::find_by_id(id.clone())
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
+
+    let mut conversation: conversation::ActiveModel = conversation.into();
+
+    if let Some(title) = data.title {
+        let title_locked = data
+            .title_locked
+            .unwrap_or_else(|| !title.trim().is_empty());
+        conversation.title = Set(title);
+        conversation.title_locked = Set(title_locked);
+    }
+    if let Some(prompt_override) = data.prompt_override {
+        conversation.prompt_override = Set(Some(prompt_override).filter(|p| !p.trim().is_empty()));
+    }
+
+    // 更新时间只随消息变化，修改标题与设定不改变对话在列表中的位置
+    let result = conversation.update(&*conn).await?;
+    Ok(result)
+}
+
+// 删除对话，消息通过外键级联删除，它们的向量需要单独删除
+pub async fn delete_conversation(
+    client: &DbClient,
+    id: String,
+) -> Result<conversation::Model, DbErr> {
+    let conn = client.lock().await;
+    let txn = conn.begin().await?;
+
+    let conversation = Conversation::find_by_id(id.clone())
+        .one(&txn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Conversation not found".to_string()))?;
+
+    let message_ids = Message::find()
+        .select_only()
+        .column(message::Column::Id)
+        .filter(message::Column::ConversationId.eq(id))
+        .into_query();
+    delete_embeddings_in(&txn, EMBEDDING_SOURCE_MESSAGE, message_ids).await?;
+
+    let result = conversation.clone();
+    conversation.delete(&txn).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 获取对话的所有消息
+pub async fn get_messages_by_conversation_id(
+    client: &DbClient,
+    conversation_id: String,
+) -> Result<Vec<message::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    let messages = Message::find()
+        .filter(message::Column::ConversationId.eq(conversation_id))
+        .order_by_asc(message::Column::Index)
+        .all(&*conn)
+        .await?;
+
+    Ok(messages)
+}
+
+// 获取对话中界面可见的消息，学生的消息在送达前不显示
+pub async fn get_visible_messages_by_conversation_id(
+    client: &DbClient,
+    conversation_id: String,
+) -> Result<Vec<message::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    Message::find()
+        .filter(message::Column::ConversationId.eq(conversation_id))
+        .filter(delivered_condition())
+        .order_by_asc(message::Column::Index)
+        .all(&*conn)
+        .await
+}
+
+// 基于游标获取对话的消息，学生的消息在送达前不显示
+//
+// 默认从最新的消息开始倒序返回，传入`before_index`向上加载更早的消息；
+// 只传入`after_index`时按正序返回该索引之后的新消息。
+pub async fn get_messages_by_conversation_id_with_cursor(
+    client: &DbClient,
+    conversation_id: String,
+    before_index: Option<i32>,
+    after_index: Option<i32>,
+    limit: u64,
+) -> Result<MessagePage, DbErr> {
+    let conn = client.lock().await;
+
+    let mut query = Message::find()
+        .filter(message::Column::ConversationId.eq(conversation_id))
+        .filter(delivered_condition());
+
+    if let Some(before_index) = before_index {
+        query = query.filter(message::Column::Index.lt(before_index));
+    }
+    if let Some(after_index) = after_index {
+        query = query.filter(message::Column::Index.gt(after_index));
+    }
+
+    query = if before_index.is_none() && after_index.is_some() {
+        query.order_by_asc(message::Column::Index)
+    } else {
+        query.order_by_desc(message::Column::Index)
+    };
+
+    // 多取一条用于判断是否还有更多消息
+    let mut messages = query.limit(limit + 1).all(&*conn).await?;
+    let has_more = messages.len() as u64 > limit;
+    messages.truncate(limit as usize);
+
+    Ok(MessagePage { messages, has_more })
+}
+
+// 创建新消息
+pub async fn create_message(client: &DbClient, data: MessageData) -> Result<message::Model, DbErr> {
+    let conn = client.lock().await;
+
+    // 获取当前对话中最大的索引值
+    let max_index = Message::find()
+        .filter(message::Column::ConversationId.eq(data.conversation_id.clone()))
+        .order_by_desc(message::Column::Index)
+        .one(&*conn)
+        .await?
+        .map(|m| m.index)
+        .unwrap_or(-1);
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    // 单独创建的学生消息立即送达，只有逐条送达的回复在送达前为空
+    let delivered_at = (data.role == "assistant").then_some(now);
+
+    let message = message::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        conversation_id: Set(data.conversation_id),
+        role: Set(data.role),
+        content: Set(data.content),
+        name: Set(data.name.unwrap_or_default()),
+        created_at: Set(now),
+        index: Set(data.index.unwrap_or(max_index + 1)),
+        prompt_revision_id: Set(data.prompt_revision_id),
+        proactive: Set(data.proactive),
+        turn_id: Set(data.turn_id),
+        read_at: Set(None),
+        delivered_at: Set(delivered_at),
+    };
+
+    let result = message.insert(&*conn).await?;
+
+    // 更新对话的更新时间
+    touch_conversation(&*conn, &result.conversation_id).await?;
+
+    Ok(result)
+}
+
+// 将一次回复拆分出的多条消息依次保存为连续的消息，共用同一个新生成的轮次ID
+pub async fn create_turn_messages(
+    client: &DbClient,
+    messages: Vec<MessageData>,
+) -> Result<Vec<message::Model>, DbErr> {
+    let conn = client.lock().await;
+    let txn = conn.begin().await?;
+
+    let turn_id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+
+    let mut results: Vec<message::Model> = Vec::new();
+    for data in messages {
+        let index = match results.last() {
+            Some(previous) => previous.index + 1,
+            None => Message::find()
+                .filter(message::Column::ConversationId.eq(data.conversation_id.clone()))
+                .order_by_desc(message::Column::Index)
+                .one(&txn)
+                .await?
+                .map(|m| m.index + 1)
+                .unwrap_or(0),
+        };
+
+        let message = message::ActiveModel {
+            id: Default::default(), // 自动生成ID
+            conversation_id: Set(data.conversation_id),
+            role: Set(data.role),
+            content: Set(data.content),
+            name: Set(data.name.unwrap_or_default()),
+            created_at: Set(now),
+            index: Set(data.index.unwrap_or(index)),
+            prompt_revision_id: Set(data.prompt_revision_id),
+            proactive: Set(data.proactive),
+            turn_id: Set(Some(turn_id.clone())),
+            read_at: Set(None),
+            delivered_at: Set(None),
+        };
+        results.push(message.insert(&txn).await?);
+    }
+
+    if let Some(last) = results.last() {
+        touch_conversation(&txn, &last.conversation_id).await?;
+    }
+    txn.commit().await?;
+
+    Ok(results)
+}
+
+// 将对话中还没有被读到的用户消息标记为已读，返回已读的时间
+pub async fn mark_user_messages_read(
+    client: &DbClient,
+    conversation_id: String,
+) -> Result<DateTimeWithTimeZone, DbErr> {
+    let conn = client.lock().await;
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+
+    Message::update_many()
+        .col_expr(message::Column::ReadAt, Expr::value(now))
+        .filter(message::Column::ConversationId.eq(conversation_id))
+        .filter(message::Column::Role.eq("user"))
+        .filter(message::Column::ReadAt.is_null())
+        .exec(&*conn)
+        .await?;
+
+    Ok(now)
+}
+
+// 将消息标记为已送达，返回更新后的消息
+pub async fn mark_messages_delivered(
+    client: &DbClient,
+    ids: Vec<i32>,
+) -> Result<Vec<message::Model>, DbErr> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let conn = client.lock().await;
+
+    Message::update_many()
+        .col_expr(
+            message::Column::DeliveredAt,
+            Expr::value(chrono::Utc::now().naive_utc().and_utc().fixed_offset()),
+        )
+        .filter(message::Column::Id.is_in(ids.clone()))
+        .exec(&*conn)
+        .await?;
+
+    Message::find()
+        .filter(message::Column::Id.is_in(ids))
+        .order_by_asc(message::Column::Index)
+        .all(&*conn)
+        .await
+}
+
+// 获取指定时间之后学生主动发送的消息，按时间倒序
+pub async fn get_proactive_messages_since(
+    client: &DbClient,
+    since: DateTimeWithTimeZone,
+) -> Result<Vec<message::Model>, DbErr> {
+    let conn = client.lock().await;
+
+    Message::find()
+        .filter(message::Column::Proactive.eq(true))
+        .filter(message::Column::CreatedAt.gte(since))
+        .order_by_desc(message::Column::CreatedAt)
+        .all(&*conn)
+        .await
+}
+
+// 更新消息
+pub async fn update_message(
+    client: &DbClient,
+    id: i32,
+    data: MessageUpdateData,
+) -> Result<message::Model, DbErr> {
+    let conn = client.lock().await;
+
+    let message = Message::find_by_id(id)
+        .one(&*conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Message not found".to_string()))?;
+
+    let mut message: message::ActiveModel = message.into();
+
+    if let Some(content) = data.content {
+        message.content = Set(content);
+        // 内容变化后原有向量失效
+        delete_embedding(&*conn, EMBEDDING_SOURCE_MESSAGE, id).await?;
+    }
+    if let Some(name) = data.name {
+        message.name = Set(name);
+    }
+
+    let result = message.update(&*conn).await?;
+
+    touch_conversation(&*conn, &result.conversation_id).await?;
+
+    Ok(result)
+}
+
+// 删除消息
+pub async fn delete_message(client: &DbClient, id: i32) -> Result<message::Model, DbErr> {
+    let conn = client.lock().await;
+    let txn = conn.begin().await?;
+
+    let message = Message::find_by_id(id)
+        .one(&txn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Message not found".to_string()))?;
+
+    let result = message.clone();
+    message.delete(&txn).await?;
+    delete_embedding(&txn, EMBEDDING_SOURCE_MESSAGE, id).await?;
+
+    touch_conversation(&txn, &result.conversation_id).await?;
+    txn.commit().await?;
+
+    Ok(result)
+}
+
+// 查找自定义角色，官方学生不允许通过自定义角色接口修改
+async fn find_custom_student<C: ConnectionTrait>(
+    conn: &C,
+    id: i32,
+) -> Result<student::Model, DbErr> {
+    let student = Student::find_by_id(id)
+        .one(conn)
+        .await?
+        .ok_or_else(|| DbErr::Custom("Student not found".to_string()))?;
+
+    if !student.is_custom {
+        return Err(DbErr::Custom(
+            "Official students cannot be modified".to_string(),
+        ));
+    }
+
+    Ok(student)
+}
+
+// 更新学生prompt并记录版本
+async fn set_student_prompt<C: ConnectionTrait>(
+    conn: &C,
+    student: student::Model,
+    prompt: String,
+    note: Option<String>,
+) -> Result<student::Model, DbErr> {
+    let mut student: student::ActiveModel = student.into();
+    student.prompt = Set(prompt);
+
+    let result = student.update(conn).await?;
+    create_prompt_revision(conn, &result, note).await?;
+
+    Ok(result)
+}
+
+// 记录学生当前的prompt与使用的模板
+async fn create_prompt_revision<C: ConnectionTrait>(
+    conn: &C,
+    student: &student::Model,
+    note: Option<String>,
+) -> Result<student_prompt_revision::Model, DbErr> {
+    let template = effective_prompt_template(conn, student).await?;
+
+    let revision = student_prompt_revision::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        student_id: Set(student.id),
+        prompt: Set(student.prompt.clone()),
+        note: Set(note),
+        created_at: Set(chrono::Utc::now().naive_utc().and_utc().fixed_offset()),
+        prompt_template_id: Set(template.as_ref().map(|t| t.id)),
+        template: Set(template.map(|t| t.content)),
+    };
+
+    revision.insert(conn).await
+}
+
+// 学生的prompt或使用的模板与最新版本不同时记录新版本，返回最新版本的ID
+async fn sync_prompt_revision<C: ConnectionTrait>(
+    conn: &C,
+    student: &student::Model,
+    note: Option<String>,
+) -> Result<i32, DbErr> {
+    let template = effective_prompt_template(conn, student).await?;
+    let latest = StudentPromptRevision::find()
+        .filter(student_prompt_revision::Column::StudentId.eq(student.id))
+        .order_by_desc(student_prompt_revision::Column::Id)
+        .one(conn)
+        .await?;
+
+    let unchanged = latest.as_ref().is_some_and(|revision| {
+        revision.prompt == student.prompt
+            && revision.prompt_template_id == template.as_ref().map(|t| t.id)
+            && revision.template.as_deref() == template.as_ref().map(|t| t.content.as_str())
+    });
+    match latest {
+        Some(revision) if unchanged => Ok(revision.id),
+        _ => Ok(create_prompt_revision(conn, student, note).await?.id),
+    }
+}
+
+// 学生实际使用的prompt模板：指定模板不存在时回退到默认模板，默认模板也不存在时为空
+async fn effective_prompt_template<C: ConnectionTrait>(
+    conn: &C,
+    student: &student::Model,
+) -> Result<Option<prompt_template::Model>, DbErr> {
+    if let Some(template_id) = student.prompt_template_id {
+        if let Some(template) = PromptTemplate::find_by_id(template_id).one(conn).await? {
+            return Ok(Some(template));
+        }
+    }
+
+    PromptTemplate::find()
+        .filter(prompt_template::Column::Name.eq(prompt::DEFAULT_TEMPLATE_NAME))
+        .one(conn)
+        .await
+}
+
+// 为使用某个模板的学生记录新版本，模板修改或删除后调用
+async fn sync_template_revisions<C: ConnectionTrait>(
+    conn: &C,
+    students: Vec<student::Model>,
+    note: String,
+) -> Result<(), DbErr> {
+    for student in students {
+        sync_prompt_revision(conn, &student, Some(note.clone())).await?;
+    }
+
+    Ok(())
+}
+
+// 使用某个模板的学生，默认模板还包括未指定模板的学生
+async fn students_using_template<C: ConnectionTrait>(
+    conn: &C,
+    template: &prompt_template::Model,
+) -> Result<Vec<student::Model>, DbErr> {
+    let mut condition = Condition::any().add(student::Column::PromptTemplateId.eq(template.id));
+    if template.name == prompt::DEFAULT_TEMPLATE_NAME {
+        condition = condition.add(student::Column::PromptTemplateId.is_null());
+    }
+
+    Student::find().filter(condition).all(conn).await
+}
+
+async fn delete_embedding<C: ConnectionTrait>(
+    conn: &C,
+    source_type: &str,
+    source_id: i32,
+) -> Result<(), DbErr> {
+    Embedding::delete_many()
+        .filter(embedding::Column::SourceType.eq(source_type))
+        .filter(embedding::Column::SourceId.eq(source_id))
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
+
+// 删除子查询选出的来源的向量，embedding表没有外键，删除来源时需要在同一事务中调用
+async fn delete_embeddings_in<C: ConnectionTrait>(
+    conn: &C,
+    source_type: &str,
+    source_ids: SelectStatement,
+) -> Result<(), DbErr> {
+    Embedding::delete_many()
+        .filter(embedding::Column::SourceType.eq(source_type))
+        .filter(embedding::Column::SourceId.in_subquery(source_ids))
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn insert_memory<C: ConnectionTrait>(
+    conn: &C,
+    student_id: i32,
+    content: String,
+    importance: i32,
+) -> Result<student_memory::Model, DbErr> {
+    if content.trim().is_empty() {
+        return Err(DbErr::Custom("Memory content cannot be empty".to_string()));
+    }
+
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+    let memory_model = student_memory::ActiveModel {
+        id: Default::default(), // 自动生成ID
+        student_id: Set(student_id),
+        content: Set(content.trim().to_string()),
+        importance: Set(importance.clamp(memory::MIN_IMPORTANCE, memory::MAX_IMPORTANCE)),
+        created_at: Set(now),
+        updated_at: Set(now),
+        last_used_at: Set(None),
+    };
+
+    memory_model.insert(conn).await
+}
+
+fn metadata_to_string(metadata: Option<serde_json::Value>) -> String {
+    metadata
+        .map(|m| m.to_string())
+        .unwrap_or_else(|| "{}".to_string())
+}
+
+// 对话列表查询：一次性关联学生、最后一条消息与未读数
+//
+// 与delivered_condition相同，还在输入中的回复不作为最后一条消息，也不计入未读
+fn conversations_with_student_query() -> SelectTwo<Conversation, Student> {
+    const DELIVERED: &str = "(m.role <> 'assistant' OR m.delivered_at IS NOT NULL)";
+    let last_message = |column: &str| {
+        Expr::cust(format!(
+            "(SELECT m.{column} FROM message m \
+             WHERE m.conversation_id = conversation.id AND m.role <> 'system' \
+             AND {DELIVERED} ORDER BY m.\"index\" DESC LIMIT 1)"
+        ))
+    };
+
+    Conversation::find()
+        .find_also_related(Student)
+        .expr_as(last_message("content"), "last_message_content")
+        .expr_as(last_message("role"), "last_message_role")
+        .expr_as(last_message("created_at"), "last_message_at")
+        .expr_as(
+            Expr::cust(format!(
+                "(SELECT COUNT(*) FROM message m \
+                 WHERE m.conversation_id = conversation.id AND m.role = 'assistant' \
+                 AND {DELIVERED} AND m.\"index\" > conversation.last_read_index)"
+            )),
+            "unread_count",
+        )
+}
+
+// 已送达的消息，用户消息不需要送达
+fn delivered_condition() -> Condition {
+    Condition::any()
+        .add(message::Column::Role.ne("assistant"))
+        .add(message::Column::DeliveredAt.is_not_null())
+}
+
+// 将对话的更新时间设置为当前时间
+async fn touch_conversation<C: ConnectionTrait>(
+    conn: &C,
+    conversation_id: &str,
+) -> Result<(), DbErr> {
+    let now = chrono::Utc::now().naive_utc().and_utc().fixed_offset();
+
+    Conversation::update_many()
+        .col_expr(conversation::Column::UpdatedAt, Expr::value(now))
+        .filter(conversation::Column::Id.eq(conversation_id))
+        .exec(conn)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use entity::message;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Serialize;
+use std::time::Duration;
+
+// 学生读到了用户的消息
+pub const MESSAGE_READ_EVENT: &str = "message-read";
+// 学生正在输入
+pub const MESSAGE_TYPING_EVENT: &str = "message-typing";
+// 学生的一条消息送达
+pub const MESSAGE_DELIVERED_EVENT: &str = "message-delivered";
+
+// 默认的打字速度（字/分钟）
+pub const DEFAULT_TYPING_SPEED: i32 = 240;
+
+// 读到用户消息前的等待时间：基础时间加上按字数计算的阅读时间
+const READ_BASE_MS: u64 = 800;
+const READ_PER_CHAR_MS: u64 = 30;
+const MAX_READ_MS: u64 = 5000;
+
+// 每条消息打字时间的上下限
+const MIN_TYPING_MS: u64 = 600;
+const MAX_TYPING_MS: u64 = 8000;
+
+// 读到用户消息前的等待时间
+pub fn read_delay(content: &str) -> Duration {
+    let chars = content.chars().count() as u64;
+    Duration::from_millis((READ_BASE_MS + chars * READ_PER_CHAR_MS).min(MAX_READ_MS))
+}
+
+// 按学生的打字速度计算输入一条消息所需的时间，速度为空或无效时使用默认速度
+pub fn typing_duration(content: &str, typing_speed: Option<i32>) -> Duration {
+    let speed = typing_speed
+        .filter(|speed| *speed > 0)
+        .unwrap_or(DEFAULT_TYPING_SPEED) as u64;
+    let chars = content.chars().count() as u64;
+    Duration::from_millis((chars * 60_000 / speed).clamp(MIN_TYPING_MS, MAX_TYPING_MS))
+}
+
+// 检查打字速度，必须为正数
+pub fn validate_typing_speed(typing_speed: Option<i32>) -> Result<(), String> {
+    match typing_speed {
+        Some(speed) if speed <= 0 => Err(format!("Invalid typing speed: {}", speed)),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageReadEvent {
+    pub conversation_id: String,
+    pub read_at: DateTimeWithTimeZone,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageTypingEvent {
+    pub conversation_id: String,
+}
+
+// 一次回复拆分出的多条消息逐条送达，index从0开始
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageDeliveredEvent {
+    pub conversation_id: String,
+    pub turn_id: String,
+    pub index: usize,
+    pub total: usize,
+    pub message: message::Model,
+}
@@ -1,8 +1,28 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 use tauri::{path::BaseDirectory, Manager};
 
+mod avatar;
+mod avatar_cache;
+mod bond;
+mod bubble;
+mod calendar;
 mod commands;
-mod db;
+pub mod dataset;
+pub mod db;
+mod delivery;
+mod diff;
+mod lore;
+mod memory;
+mod proactive;
+mod profile;
+mod prompt;
+mod retrieval;
+mod suggestion;
+mod text;
+mod time_context;
+mod title;
+mod vector;
+mod world_info;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -10,6 +30,41 @@ pub fn run() {
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_os::init())
+        // 通过自定义协议提供头像，远程头像优先使用本地缓存
+        .register_asynchronous_uri_scheme_protocol(
+            avatar_cache::AVATAR_SCHEME,
+            |ctx, request, responder| {
+                let cache = ctx
+                    .app_handle()
+                    .try_state::<avatar_cache::AvatarCache>()
+                    .map(|cache| cache.inner().clone());
+                let src = avatar_cache::source_from_path(request.uri().path());
+                tauri::async_runtime::spawn(async move {
+                    let image = match cache {
+                        Some(cache) => cache.load(&src).await,
+                        None => avatar_cache::AvatarImage::placeholder(),
+                    };
+                    let response = tauri::http::Response::builder()
+                        .header(tauri::http::header::CONTENT_TYPE, image.content_type)
+                        .body(image.bytes)
+                        .unwrap_or_else(|e| {
+                            // 远程返回的Content-Type不合法时改用占位图
+                            println!("[ERROR] 构造头像响应失败: {}", e);
+                            let placeholder = avatar_cache::AvatarImage::placeholder();
+                            let mut response = tauri::http::Response::new(placeholder.bytes);
+                            if let Ok(value) =
+                                tauri::http::HeaderValue::from_str(&placeholder.content_type)
+                            {
+                                response
+                                    .headers_mut()
+                                    .insert(tauri::http::header::CONTENT_TYPE, value);
+                            }
+                            response
+                        });
+                    responder.respond(response);
+                });
+            },
+        )
         .setup(|app| {
             #[cfg(debug_assertions)] // only include this code on debug builds
             {
@@ -18,22 +73,46 @@ pub fn run() {
                     window.open_devtools();
                 }
             }
-            // 初始化数据库
             let handle = app.handle();
+            // 初始化头像缓存
+            let app_data_dir = handle.path().app_data_dir()?;
+            handle.manage(avatar_cache::AvatarCache::new(
+                app_data_dir.join(avatar_cache::AVATAR_CACHE_DIR),
+                app_data_dir.join(avatar::CUSTOM_AVATAR_DIR),
+            ));
+            // 初始化数据库
             let db_path = handle
                 .path()
                 .resolve("data/db.sqlite", BaseDirectory::Resource)?;
-            let students_content_path = handle
-                .path()
-                .resolve("data/students.json", BaseDirectory::Resource)?;
-            let students_data_path = handle
-                .path()
-                .resolve("data/students_min.json", BaseDirectory::Resource)?;
+            let dataset_paths = dataset::DatasetPaths {
+                manifest: handle
+                    .path()
+                    .resolve("data/manifest.json", BaseDirectory::Resource)?,
+                students_content: handle
+                    .path()
+                    .resolve("data/students.json", BaseDirectory::Resource)?,
+                students_data: handle
+                    .path()
+                    .resolve("data/students_min.json", BaseDirectory::Resource)?,
+            };
             tauri::async_runtime::block_on(async {
-                let db_client = db::init_db(db_path, students_content_path, students_data_path)
-                    .await
-                    .expect("Failed to initialize database");
-                handle.manage(db_client);
+                // 初始化失败时不退出，由前端通过get_startup_status展示问题
+                let status = match db::init_db(db_path, &dataset_paths).await {
+                    Ok((db_client, status)) => {
+                        // 启动主动消息的定时检查
+                        tauri::async_runtime::spawn(commands::run_proactive_scheduler(
+                            handle.clone(),
+                            db_client.clone(),
+                        ));
+                        handle.manage(db_client);
+                        status
+                    }
+                    Err(e) => {
+                        println!("[ERROR] 数据库初始化失败: {}", e);
+                        db::StartupStatus::degraded(format!("数据库初始化失败: {}", e))
+                    }
+                };
+                handle.manage(status);
             });
             Ok(())
         })
@@ -42,11 +121,62 @@ pub fn run() {
             commands::get_conversation_by_id,
             commands::create_conversation,
             commands::update_conversation,
+            commands::generate_conversation_title,
             commands::delete_conversation,
+            commands::mark_conversation_read,
             commands::get_messages_by_conversation_id,
-            commands::get_messages_by_conversation_id_with_pagination,
+            commands::get_messages_by_conversation_id_with_cursor,
             commands::create_message,
+            commands::update_message,
+            commands::delete_message,
             commands::chat_with_llm,
+            commands::suggest_replies,
+            commands::get_student_profile,
+            commands::prefetch_avatars,
+            commands::get_student_avatar_variants,
+            commands::set_conversation_avatar,
+            commands::get_student_bond,
+            commands::get_student_bonds,
+            commands::get_student_memories,
+            commands::create_student_memory,
+            commands::update_student_memory,
+            commands::delete_student_memory,
+            commands::get_lorebooks,
+            commands::create_lorebook,
+            commands::update_lorebook,
+            commands::delete_lorebook,
+            commands::get_lorebook_entries,
+            commands::create_lorebook_entry,
+            commands::update_lorebook_entry,
+            commands::delete_lorebook_entry,
+            commands::get_upcoming_events,
+            commands::get_anniversaries,
+            commands::create_anniversary,
+            commands::update_anniversary,
+            commands::delete_anniversary,
+            commands::export_calendar_ics,
+            commands::get_personas,
+            commands::create_persona,
+            commands::update_persona,
+            commands::delete_persona,
+            commands::set_active_persona,
+            commands::set_conversation_persona,
+            commands::get_startup_status,
+            commands::create_custom_student,
+            commands::update_custom_student,
+            commands::delete_custom_student,
+            commands::get_prompt_templates,
+            commands::create_prompt_template,
+            commands::update_prompt_template,
+            commands::delete_prompt_template,
+            commands::set_student_prompt_template,
+            commands::set_student_typing_speed,
+            commands::preview_prompt_template,
+            commands::debug_lore_passages,
+            commands::update_student_prompt,
+            commands::get_student_prompt_revisions,
+            commands::diff_student_prompt_revisions,
+            commands::restore_student_prompt_revision,
             commands::set_store,
             commands::get_store
         ])
@@ -0,0 +1,280 @@
+use crate::text;
+use entity::lore_chunk;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+// 每次注入prompt的资料片段数量上限
+pub const MAX_LORE_PASSAGES: usize = 4;
+
+// 单个片段的目标长度（字符数），单行超出时不再切分
+const CHUNK_CHARS: usize = 400;
+
+// BM25参数
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+// 从档案文本中切分出的片段
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    // 所在章节的标题，多级标题用「 > 」连接
+    pub heading: String,
+    pub content: String,
+}
+
+// 被选中注入prompt的片段
+#[derive(Debug, Clone, Serialize)]
+pub struct LorePassage {
+    pub chunk_id: i32,
+    pub position: i32,
+    pub heading: String,
+    pub content: String,
+    pub score: f64,
+}
+
+// 按Markdown标题切分档案文本，章节过长时按行合并为约CHUNK_CHARS字的片段
+pub fn chunk_content(content: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut headings: Vec<String> = Vec::new();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut length = 0;
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('#') {
+            flush_chunk(&headings, &mut lines, &mut length, &mut chunks);
+            let level = line.chars().take_while(|c| *c == '#').count();
+            headings.truncate(level - 1);
+            headings.push(line.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+
+        let line_length = line.chars().count();
+        if length > 0 && length + line_length > CHUNK_CHARS {
+            flush_chunk(&headings, &mut lines, &mut length, &mut chunks);
+        }
+        lines.push(line);
+        length += line_length;
+    }
+    flush_chunk(&headings, &mut lines, &mut length, &mut chunks);
+
+    chunks
+}
+
+fn flush_chunk(
+    headings: &[String],
+    lines: &mut Vec<&str>,
+    length: &mut usize,
+    chunks: &mut Vec<Chunk>,
+) {
+    if lines.is_empty() {
+        return;
+    }
+
+    chunks.push(Chunk {
+        heading: headings
+            .iter()
+            .filter(|h| !h.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" > "),
+        content: lines.join("\n"),
+    });
+    lines.clear();
+    *length = 0;
+}
+
+// 统计片段的词元出现次数与词元总数，标题也参与检索
+pub fn index_terms(chunk: &Chunk) -> (HashMap<String, u32>, usize) {
+    let tokens = text::tokenize(&format!("{}\n{}", chunk.heading, chunk.content));
+    let length = tokens.len();
+
+    let mut terms = HashMap::new();
+    for token in tokens {
+        *terms.entry(token).or_insert(0) += 1;
+    }
+
+    (terms, length)
+}
+
+// 按BM25为片段打分，返回得分大于0的片段，按得分降序
+pub fn search(chunks: &[lore_chunk::Model], query: &str, limit: usize) -> Vec<LorePassage> {
+    let query_terms: HashSet<String> = text::tokenize(query).into_iter().collect();
+    if chunks.is_empty() || query_terms.is_empty() {
+        return Vec::new();
+    }
+
+    let indexed: Vec<HashMap<String, u32>> = chunks
+        .iter()
+        .map(|chunk| serde_json::from_str(&chunk.terms).unwrap_or_default())
+        .collect();
+
+    let count = chunks.len() as f64;
+    let average_length = (chunks.iter().map(|c| f64::from(c.length)).sum::<f64>() / count).max(1.0);
+
+    let idf: HashMap<&str, f64> = query_terms
+        .iter()
+        .map(|term| {
+            let df = indexed.iter().filter(|t| t.contains_key(term)).count() as f64;
+            (term.as_str(), (1.0 + (count - df + 0.5) / (df + 0.5)).ln())
+        })
+        .collect();
+
+    let mut passages: Vec<LorePassage> = chunks
+        .iter()
+        .zip(&indexed)
+        .map(|(chunk, terms)| {
+            let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * f64::from(chunk.length) / average_length);
+            let score = idf
+                .iter()
+                .filter_map(|(term, idf)| {
+                    let tf = f64::from(*terms.get(*term)?);
+                    Some(idf * tf * (BM25_K1 + 1.0) / (tf + norm))
+                })
+                .sum();
+            passage(chunk, score)
+        })
+        .filter(|p| p.score > 0.0)
+        .collect();
+    passages.sort_by(|a, b| b.score.total_cmp(&a.score));
+    passages.truncate(limit);
+
+    passages
+}
+
+// 挑选要注入prompt的片段：开头的基本信息始终保留，其余按与当前消息的相关度选取，按原文顺序排列
+pub fn select_passages(
+    chunks: &[lore_chunk::Model],
+    query: &str,
+    limit: usize,
+) -> Vec<LorePassage> {
+    let mut passages = search(chunks, query, limit);
+
+    if let Some(lead) = chunks.iter().min_by_key(|c| c.position) {
+        if !passages.iter().any(|p| p.chunk_id == lead.id) {
+            if passages.len() >= limit {
+                passages.pop();
+            }
+            passages.push(passage(lead, 0.0));
+        }
+    }
+    passages.sort_by_key(|p| p.position);
+
+    passages
+}
+
+fn passage(chunk: &lore_chunk::Model, score: f64) -> LorePassage {
+    LorePassage {
+        chunk_id: chunk.id,
+        position: chunk.position,
+        heading: chunk.heading.clone(),
+        content: chunk.content.clone(),
+        score,
+    }
+}
+
+// 片段在prompt中的文本
+pub fn format_passages(passages: &[LorePassage]) -> String {
+    passages
+        .iter()
+        .map(|p| {
+            if p.heading.is_empty() {
+                p.content.clone()
+            } else {
+                format!("【{}】\n{}", p.heading, p.content)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(chunks: Vec<Chunk>) -> Vec<lore_chunk::Model> {
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(position, chunk)| {
+                let (terms, length) = index_terms(&chunk);
+                lore_chunk::Model {
+                    id: position as i32 + 100,
+                    student_id: 1,
+                    position: position as i32,
+                    heading: chunk.heading,
+                    content: chunk.content,
+                    terms: serde_json::to_string(&terms).unwrap(),
+                    length: length as i32,
+                }
+            })
+            .collect()
+    }
+
+    const CONTENT: &str = "# 优香个人档案
+        ## 基本信息
+        - 姓名： 早濑优香
+        ## 角色语音
+        ### 平常语音
+        计算一下今天的预算。
+        ### 纪念大厅语音
+        老师，又乱花钱了吧？
+        ## 爱好
+        喜欢计算与整理账目";
+
+    #[test]
+    fn chunks_by_heading_level() {
+        let chunks = chunk_content(CONTENT);
+
+        let headings: Vec<&str> = chunks.iter().map(|c| c.heading.as_str()).collect();
+        assert_eq!(
+            headings,
+            [
+                "优香个人档案 > 基本信息",
+                "优香个人档案 > 角色语音 > 平常语音",
+                "优香个人档案 > 角色语音 > 纪念大厅语音",
+                "优香个人档案 > 爱好",
+            ]
+        );
+        assert_eq!(chunks[0].content, "- 姓名： 早濑优香");
+    }
+
+    #[test]
+    fn splits_long_sections() {
+        let line = "长".repeat(CHUNK_CHARS / 2);
+        let chunks = chunk_content(&format!("## 长章节\n{line}\n{line}\n{line}"));
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.heading == "长章节"));
+        assert_eq!(chunks[0].content, format!("{line}\n{line}"));
+    }
+
+    #[test]
+    fn search_ranks_matching_chunks() {
+        let chunks = stored(chunk_content(CONTENT));
+
+        let passages = search(&chunks, "优香喜欢整理账目吗", 2);
+        assert_eq!(passages[0].position, 3);
+        assert!(passages.windows(2).all(|p| p[0].score >= p[1].score));
+        assert!(search(&chunks, "？？", 2).is_empty());
+    }
+
+    #[test]
+    fn select_passages_keeps_lead_chunk() {
+        let chunks = stored(chunk_content(CONTENT));
+
+        let passages = select_passages(&chunks, "又乱花钱", 1);
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].position, 0);
+
+        let passages = select_passages(&chunks, "今天的预算，又乱花钱", 3);
+        let positions: Vec<i32> = passages.iter().map(|p| p.position).collect();
+        assert_eq!(positions, [0, 1, 2]);
+
+        let passages = select_passages(&chunks, "天气", MAX_LORE_PASSAGES);
+        assert_eq!(passages.len(), 1);
+        assert_eq!(passages[0].chunk_id, chunks[0].id);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::text;
+use entity::student_memory;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// 每次注入prompt的记忆数量上限
+pub const MAX_INJECTED_MEMORIES: usize = 8;
+
+// 记忆重要程度的范围
+pub const MIN_IMPORTANCE: i32 = 1;
+pub const MAX_IMPORTANCE: i32 = 5;
+
+// 时效得分减半所需的天数
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+// LLM从对话中提取的一条事实
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedFact {
+    pub content: String,
+    #[serde(default = "default_importance")]
+    pub importance: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Extraction {
+    #[serde(default)]
+    facts: Vec<ExtractedFact>,
+}
+
+fn default_importance() -> i32 {
+    MIN_IMPORTANCE
+}
+
+// 让LLM提取长期记忆的提示词，要求输出JSON
+pub fn extraction_prompt(
+    student_name: &str,
+    user_message: &str,
+    reply: &str,
+    existing: &[student_memory::Model],
+) -> String {
+    let existing = if existing.is_empty() {
+        "无".to_string()
+    } else {
+        existing
+            .iter()
+            .map(|m| format!("- {}", m.content))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    format!(
+        "你负责为{name}整理关于用户的长期记忆。请从下面这轮对话中提取值得长期记住的事实，\
+例如用户的喜好、经历、计划、约定以及提到的重要人物。只提取对话中明确提到、之后仍然有用的信息，\
+忽略寒暄和一次性的内容，不要重复已有记忆。每条事实用一句话描述，以「用户」指代用户。\n\n\
+已有记忆：\n{existing}\n\n对话：\n用户：{user}\n{name}：{reply}\n\n\
+只输出JSON，不要输出其他内容，格式为：\
+{{\"facts\": [{{\"content\": \"用户喜欢猫\", \"importance\": 3}}]}}\n\
+importance为{min}到{max}的整数，越重要越大；没有值得记住的内容时输出{{\"facts\": []}}",
+        name = student_name,
+        existing = existing,
+        user = user_message,
+        reply = reply,
+        min = MIN_IMPORTANCE,
+        max = MAX_IMPORTANCE,
+    )
+}
+
+// 解析LLM输出的JSON，兼容包裹在代码块中的输出
+pub fn parse_extraction(output: &str) -> Result<Vec<ExtractedFact>, serde_json::Error> {
+    let json = match (output.find('{'), output.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &output[start..=end],
+        _ => output,
+    };
+    let extraction: Extraction = serde_json::from_str(json)?;
+
+    Ok(extraction
+        .facts
+        .into_iter()
+        .map(|fact| ExtractedFact {
+            content: fact.content.trim().to_string(),
+            importance: fact.importance.clamp(MIN_IMPORTANCE, MAX_IMPORTANCE),
+        })
+        .filter(|fact| !fact.content.is_empty())
+        .collect())
+}
+
+// 判断内容是否与已有记忆重复，忽略空白与标点
+pub fn is_duplicate(content: &str, existing: &[student_memory::Model]) -> bool {
+    let normalized = normalize(content);
+    existing.iter().any(|m| normalize(&m.content) == normalized)
+}
+
+fn normalize(content: &str) -> String {
+    content.chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+// 按重要程度、时效与当前消息的相关度挑选要注入prompt的记忆
+//
+// similarities为记忆与当前消息的向量相似度，没有向量的记忆按词元重合度计算相关度
+pub fn select_relevant(
+    memories: Vec<student_memory::Model>,
+    query: &str,
+    similarities: &HashMap<i32, f32>,
+    now: DateTimeWithTimeZone,
+    limit: usize,
+) -> Vec<student_memory::Model> {
+    let query_tokens: HashSet<String> = text::tokenize(query).into_iter().collect();
+
+    let mut scored: Vec<(f64, student_memory::Model)> = memories
+        .into_iter()
+        .map(|memory| {
+            let relevance = match similarities.get(&memory.id) {
+                Some(similarity) => f64::from(similarity.max(0.0)),
+                None => token_overlap(&memory.content, &query_tokens),
+            };
+            (score(&memory, relevance, now), memory)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, memory)| memory)
+        .collect()
+}
+
+fn score(memory: &student_memory::Model, relevance: f64, now: DateTimeWithTimeZone) -> f64 {
+    let importance = f64::from(memory.importance) / f64::from(MAX_IMPORTANCE);
+
+    // 时效只看记忆被写入或修改的时间，被注入prompt不算，否则已选中的记忆会一直被选中
+    let days = (now - memory.updated_at).num_seconds().max(0) as f64 / 86400.0;
+    let recency = 0.5f64.powf(days / RECENCY_HALF_LIFE_DAYS);
+
+    importance * 0.3 + recency * 0.2 + relevance * 0.5
+}
+
+// 记忆中出现在当前消息里的词元比例
+fn token_overlap(content: &str, query_tokens: &HashSet<String>) -> f64 {
+    let tokens: HashSet<String> = text::tokenize(content).into_iter().collect();
+    if tokens.is_empty() {
+        return 0.0;
+    }
+
+    tokens.intersection(query_tokens).count() as f64 / tokens.len() as f64
+}
+
+// 记忆段落的内容
+pub fn format_memories(memories: &[student_memory::Model]) -> String {
+    memories
+        .iter()
+        .map(|m| format!("- {}", m.content))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
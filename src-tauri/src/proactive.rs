@@ -0,0 +1,163 @@
+use crate::bond;
+use chrono::{DateTime, FixedOffset, NaiveTime, TimeDelta};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// 主动消息事件，前端据此刷新聊天列表
+pub const PROACTIVE_MESSAGE_EVENT: &str = "proactive-message";
+
+// 不活跃时长超过此值后不再增加被选中的权重
+const MAX_INACTIVE_HOURS: f64 = 72.0;
+
+// 主动消息的设置，对应settings.json中的键
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProactiveConfig {
+    // proactive_messages，为true时启用
+    pub enabled: bool,
+    // proactive_check_minutes，检查的间隔
+    pub check_interval_minutes: u64,
+    // proactive_min_interval_minutes，两条主动消息之间的最短间隔
+    pub min_interval_minutes: i64,
+    // proactive_daily_limit，24小时内最多发送的主动消息数量
+    pub daily_limit: usize,
+    // proactive_inactive_hours，对话至少沉寂多久才会主动发消息
+    pub min_inactive_hours: i64,
+    // proactive_quiet_start与proactive_quiet_end，格式为HH:MM，此时段内不发送
+    pub quiet_start: NaiveTime,
+    pub quiet_end: NaiveTime,
+    // proactive_students，允许主动发消息的学生ID，以逗号分隔，为空时不限制
+    pub student_ids: Vec<i32>,
+}
+
+impl Default for ProactiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            check_interval_minutes: 15,
+            min_interval_minutes: 120,
+            daily_limit: 3,
+            min_inactive_hours: 6,
+            quiet_start: NaiveTime::from_hms_opt(23, 0, 0).unwrap_or_default(),
+            quiet_end: NaiveTime::from_hms_opt(8, 0, 0).unwrap_or_default(),
+            student_ids: Vec::new(),
+        }
+    }
+}
+
+impl ProactiveConfig {
+    // 从设置中读取，缺失或格式错误的项使用默认值
+    pub fn from_settings(settings: &HashMap<&str, String>) -> Self {
+        let default = Self::default();
+        let get = |key: &str| settings.get(key).map(|v| v.trim());
+        let time = |key: &str| get(key).and_then(|v| NaiveTime::parse_from_str(v, "%H:%M").ok());
+
+        Self {
+            enabled: get("proactive_messages") == Some("true"),
+            check_interval_minutes: get("proactive_check_minutes")
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default.check_interval_minutes),
+            min_interval_minutes: get("proactive_min_interval_minutes")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_interval_minutes),
+            daily_limit: get("proactive_daily_limit")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.daily_limit),
+            min_inactive_hours: get("proactive_inactive_hours")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.min_inactive_hours),
+            quiet_start: time("proactive_quiet_start").unwrap_or(default.quiet_start),
+            quiet_end: time("proactive_quiet_end").unwrap_or(default.quiet_end),
+            student_ids: get("proactive_students")
+                .map(|v| {
+                    v.split(',')
+                        .filter_map(|id| id.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    // 当前是否处于免打扰时段，支持跨越午夜的时段
+    pub fn in_quiet_hours(&self, time: NaiveTime) -> bool {
+        if self.quiet_start <= self.quiet_end {
+            self.quiet_start <= time && time < self.quiet_end
+        } else {
+            time >= self.quiet_start || time < self.quiet_end
+        }
+    }
+
+    // 是否已达到发送频率上限，recent_sent为最近24小时内主动消息的发送时间
+    pub fn rate_limited(
+        &self,
+        now: DateTime<FixedOffset>,
+        recent_sent: &[DateTime<FixedOffset>],
+    ) -> bool {
+        if recent_sent.len() >= self.daily_limit {
+            return true;
+        }
+
+        recent_sent
+            .iter()
+            .any(|sent| now - *sent < TimeDelta::minutes(self.min_interval_minutes))
+    }
+}
+
+// 可以主动发消息的对话
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub conversation_id: String,
+    pub student_id: i32,
+    pub last_message_at: DateTime<FixedOffset>,
+    pub bond_level: i32,
+    // 今天是否是与该学生相关的特别日子且还没有聊过天
+    pub occasion: bool,
+}
+
+// 在沉寂足够久的对话中挑选一个，沉寂越久、羁绊越深越容易被选中
+//
+// 特别日子的对话不受沉寂时长的限制，并且优先于其他对话
+//
+// jitter为每个候选返回0到1之间的随机数，避免总是选中同一名学生
+pub fn pick_candidate(
+    candidates: Vec<Candidate>,
+    now: DateTime<FixedOffset>,
+    config: &ProactiveConfig,
+    mut jitter: impl FnMut() -> f64,
+) -> Option<Candidate> {
+    candidates
+        .into_iter()
+        .filter(|c| config.student_ids.is_empty() || config.student_ids.contains(&c.student_id))
+        .filter(|c| {
+            c.occasion || now - c.last_message_at >= TimeDelta::hours(config.min_inactive_hours)
+        })
+        .map(|c| {
+            let inactive_hours = (now - c.last_message_at).num_minutes() as f64 / 60.0;
+            let score = inactive_hours.min(MAX_INACTIVE_HOURS) / MAX_INACTIVE_HOURS * 0.5
+                + f64::from(c.bond_level.clamp(0, bond::MAX_LEVEL)) / f64::from(bond::MAX_LEVEL)
+                    * 0.3
+                + jitter().clamp(0.0, 1.0) * 0.2
+                + if c.occasion { 1.0 } else { 0.0 };
+            (score, c)
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, c)| c)
+}
+
+// 让LLM生成开场白的指令，作为最后一条用户消息发送
+pub fn opener_instruction(student_name: &str) -> String {
+    format!(
+        "（系统提示：用户已经有一段时间没有发消息了。请以{name}的身份主动给用户发一条MomoTalk消息，\
+结合当前的时间、你们之前聊过的内容或你自己的日常找一个自然的话题，语气符合你们现在的关系。\
+只输出消息内容，简短一些，不要提及这条提示。）",
+        name = student_name
+    )
+}
+
+// 主动消息事件的内容
+#[derive(Debug, Clone, Serialize)]
+pub struct ProactiveMessageEvent {
+    pub conversation_id: String,
+    pub student_id: i32,
+    pub content: String,
+}
@@ -0,0 +1,161 @@
+use serde::Deserialize;
+
+// 从students.json的档案文本中解析出的学生资料
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedProfile {
+    pub full_name: Option<String>,
+    pub school: Option<String>,
+    pub club: Option<String>,
+    pub grade: Option<String>,
+    pub age: Option<String>,
+    pub birthday: Option<String>,
+    pub birthday_month: Option<i32>,
+    pub birthday_day: Option<i32>,
+    pub height: Option<String>,
+    pub hobbies: Option<String>,
+    pub bio: Option<String>,
+    pub voice_actor: Option<String>,
+}
+
+// students_min.json中结构化的学生资料，各字段都可以缺失
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+pub struct StructuredProfile {
+    pub school: Option<String>,
+    pub club: Option<String>,
+    #[serde(rename = "schoolYear")]
+    pub grade: Option<String>,
+    #[serde(rename = "characterAge")]
+    pub age: Option<String>,
+    pub birthday: Option<String>,
+    #[serde(rename = "charHeightMetric")]
+    pub height: Option<String>,
+    #[serde(rename = "hobby")]
+    pub hobbies: Option<String>,
+    #[serde(rename = "characterVoice")]
+    pub voice_actor: Option<String>,
+}
+
+impl ParsedProfile {
+    // 用结构化资料中存在的字段覆盖从档案文本中解析的内容
+    pub fn with_structured(mut self, structured: &StructuredProfile) -> Self {
+        let fields = [
+            (&mut self.school, &structured.school),
+            (&mut self.club, &structured.club),
+            (&mut self.grade, &structured.grade),
+            (&mut self.age, &structured.age),
+            (&mut self.birthday, &structured.birthday),
+            (&mut self.height, &structured.height),
+            (&mut self.hobbies, &structured.hobbies),
+            (&mut self.voice_actor, &structured.voice_actor),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                *field = Some(value.to_string());
+            }
+        }
+
+        if let Some((month, day)) = self.birthday.as_deref().and_then(parse_birthday) {
+            self.birthday_month = Some(month);
+            self.birthday_day = Some(day);
+        }
+        self
+    }
+}
+
+// 解析档案中的「基本信息」部分
+//
+// 格式如下，字段值可以跨行（如个人简介）：
+// ## 基本信息
+// - 姓名： 陆八魔阿露
+// - 生日： 3月12日
+// - 声优： 鬼头明里
+// - 个人简介： 格黑娜学园所属，「便利屋68」的自称社长。
+pub fn parse_profile(content: &str) -> ParsedProfile {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let mut in_section = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("## ") {
+            in_section = line == "## 基本信息";
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+
+        let field = line
+            .strip_prefix("- ")
+            .and_then(|l| l.split_once('：').or_else(|| l.split_once(':')));
+        match (field, fields.last_mut()) {
+            (Some((key, value)), _) => {
+                fields.push((key.trim().to_string(), value.trim().to_string()));
+            }
+            // 跨行的字段值
+            (None, Some((_, value))) if !line.is_empty() => {
+                value.push('\n');
+                value.push_str(line);
+            }
+            _ => {}
+        }
+    }
+
+    let get = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+            .filter(|v| !v.is_empty())
+    };
+
+    let bio = get("个人简介");
+    let (school, club) = bio.as_deref().map(parse_affiliation).unwrap_or_default();
+    let birthday = get("生日");
+    let (birthday_month, birthday_day) = birthday
+        .as_deref()
+        .and_then(parse_birthday)
+        .map_or((None, None), |(m, d)| (Some(m), Some(d)));
+
+    ParsedProfile {
+        full_name: get("姓名"),
+        school,
+        club,
+        grade: get("年级"),
+        age: get("年龄"),
+        birthday,
+        birthday_month,
+        birthday_day,
+        height: get("身高"),
+        hobbies: get("爱好"),
+        bio,
+        voice_actor: get("声优").or_else(|| get("CV")),
+    }
+}
+
+// 从个人简介的第一句中提取学校与社团，如「格黑娜学园所属，「便利屋68」的自称社长。」
+fn parse_affiliation(bio: &str) -> (Option<String>, Option<String>) {
+    let first_line = bio.lines().next().unwrap_or_default();
+
+    let school = first_line
+        .split_once("所属")
+        .map(|(school, _)| school.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    let club = first_line
+        .split_once('「')
+        .and_then(|(_, rest)| rest.split_once('」'))
+        .map(|(club, _)| club.trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    (school, club)
+}
+
+// 解析「3月12日」格式的生日
+pub fn parse_birthday(birthday: &str) -> Option<(i32, i32)> {
+    let (month, rest) = birthday.split_once('月')?;
+    let day = rest.strip_suffix('日').unwrap_or(rest);
+    let month: i32 = month.trim().parse().ok()?;
+    let day: i32 = day.trim().parse().ok()?;
+
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((month, day))
+}
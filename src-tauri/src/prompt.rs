@@ -0,0 +1,422 @@
+use chrono::NaiveDate;
+use entity::{persona, student, student_profile};
+use std::collections::HashMap;
+use std::fmt;
+
+// 默认模板名称，数据库中始终存在且不可删除
+pub const DEFAULT_TEMPLATE_NAME: &str = "default";
+
+// 学生默认prompt模板
+pub const DEFAULT_TEMPLATE: &str =
+    "{{#if content}}基于以下检索到的关于{{name}}的信息，请以{{name}}的视角回答问题:
+{{content}}
+{{else}}请以{{name}}的视角回答问题。
+{{/if}}{{#if prompt}}
+{{prompt}}
+{{/if}}
+请记住以下几点:
+1. 完全沉浸在{{name}}的角色中，使用她的说话方式
+2. 不要提及'根据检索信息'或'作为{{name}}'等提示词
+3. 保持对话的轻松自然，就像{{user_name}}真的在和{{name}}对话
+4. 请用中文回答用户的问题，可以适当使用日语的语气词，但对话主体得使用中文回答
+5. 设定中不存在魔法师，不要使用任何魔法师的设定
+6. 今天是{{date}}";
+
+// 未设置用户名时的默认称呼
+pub const DEFAULT_USER_NAME: &str = "Sensei";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError(pub String);
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "模板解析失败: {}", self.0)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var(String),
+    If {
+        name: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+// 解析中尚未闭合的条件块
+struct Block {
+    tag: &'static str,
+    name: String,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+impl Block {
+    fn nodes(&mut self) -> &mut Vec<Node> {
+        self.otherwise.as_mut().unwrap_or(&mut self.then)
+    }
+}
+
+// 检查模板语法是否正确
+pub fn validate(template: &str) -> Result<(), TemplateError> {
+    parse(template).map(|_| ())
+}
+
+// 渲染模板
+//
+// 支持的语法：
+// * `{{name}}` - 变量替换，未定义的变量替换为空字符串
+// * `{{#if name}}...{{else}}...{{/if}}` - 变量非空时渲染第一段，否则渲染`else`段
+// * `{{#unless name}}...{{/unless}}` - 变量为空时渲染
+//
+// 学生模板可用的变量见`student_variables`
+pub fn render(template: &str, vars: &HashMap<String, String>) -> Result<String, TemplateError> {
+    let nodes = parse(template)?;
+    let mut output = String::with_capacity(template.len());
+    render_nodes(&nodes, vars, &mut output);
+    Ok(output.trim().to_string())
+}
+
+// 为指定学生渲染模板，content为注入模板的背景资料
+pub fn render_for_student(
+    template: &str,
+    student: &student::Model,
+    profile: Option<&student_profile::Model>,
+    content: &str,
+    user_name: &str,
+    today: NaiveDate,
+) -> Result<String, TemplateError> {
+    render(
+        template,
+        &student_variables(student, profile, content, user_name, today),
+    )
+}
+
+// 学生模板可用的变量
+pub fn student_variables(
+    student: &student::Model,
+    profile: Option<&student_profile::Model>,
+    content: &str,
+    user_name: &str,
+    today: NaiveDate,
+) -> HashMap<String, String> {
+    let mut vars = HashMap::from([
+        ("name".to_string(), student.name.clone()),
+        ("content".to_string(), content.to_string()),
+        ("prompt".to_string(), student.prompt.clone()),
+        ("user_name".to_string(), user_name.to_string()),
+        ("date".to_string(), today.format("%Y年%m月%d日").to_string()),
+    ]);
+
+    // 学生资料，缺失的字段视为空
+    if let Some(profile) = profile {
+        let fields = [
+            ("full_name", &profile.full_name),
+            ("school", &profile.school),
+            ("club", &profile.club),
+            ("grade", &profile.grade),
+            ("age", &profile.age),
+            ("birthday", &profile.birthday),
+            ("height", &profile.height),
+            ("hobbies", &profile.hobbies),
+            ("bio", &profile.bio),
+            ("voice_actor", &profile.voice_actor),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                vars.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+
+    vars
+}
+
+// system prompt中的一个段落
+pub struct PromptSection {
+    pub title: Option<&'static str>,
+    pub content: String,
+}
+
+impl PromptSection {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            title: None,
+            content: content.into(),
+        }
+    }
+
+    pub fn titled(title: &'static str, content: impl Into<String>) -> Self {
+        Self {
+            title: Some(title),
+            content: content.into(),
+        }
+    }
+}
+
+// 用户人设段落，让学生了解正在和谁对话
+pub fn persona_section(persona: &persona::Model) -> PromptSection {
+    let mut lines = vec![format!("名字：{}", persona.name)];
+    if !persona.address.trim().is_empty() {
+        lines.push(format!("称呼：请称呼对方为「{}」", persona.address.trim()));
+    }
+    if let Some(bio) = &persona.bio {
+        lines.push(format!("简介：{}", bio));
+    }
+    if let Some(preferences) = &persona.preferences {
+        lines.push(format!("偏好：{}", preferences));
+    }
+
+    PromptSection::titled("关于对话者", lines.join("\n"))
+}
+
+// 按顺序拼接system prompt的各个段落，忽略空段落
+pub fn assemble_system_prompt(sections: Vec<PromptSection>) -> String {
+    sections
+        .into_iter()
+        .filter(|section| !section.content.trim().is_empty())
+        .map(|section| match section.title {
+            Some(title) => format!("## {}\n{}", title, section.content.trim()),
+            None => section.content.trim().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn parse(template: &str) -> Result<Vec<Node>, TemplateError> {
+    let mut root = Vec::new();
+    let mut stack: Vec<Block> = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let text = &rest[..start];
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| TemplateError(format!("未闭合的标签: {{{{{}", after)))?;
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        let nodes = match stack.last_mut() {
+            Some(block) => block.nodes(),
+            None => &mut root,
+        };
+        if !text.is_empty() {
+            nodes.push(Node::Text(text.to_string()));
+        }
+
+        if let Some(name) = tag.strip_prefix("#if ") {
+            stack.push(Block {
+                tag: "if",
+                name: parse_name(name)?,
+                then: Vec::new(),
+                otherwise: None,
+            });
+        } else if let Some(name) = tag.strip_prefix("#unless ") {
+            stack.push(Block {
+                tag: "unless",
+                name: parse_name(name)?,
+                then: Vec::new(),
+                otherwise: None,
+            });
+        } else if tag == "else" {
+            let block = stack
+                .last_mut()
+                .ok_or_else(|| TemplateError("{{else}} 不在条件块中".to_string()))?;
+            if block.otherwise.is_some() {
+                return Err(TemplateError("条件块中出现多个 {{else}}".to_string()));
+            }
+            block.otherwise = Some(Vec::new());
+        } else if let Some(closing) = tag.strip_prefix('/') {
+            let block = stack
+                .pop()
+                .ok_or_else(|| TemplateError(format!("多余的结束标签: {{{{{}}}}}", tag)))?;
+            if closing.trim() != block.tag {
+                return Err(TemplateError(format!(
+                    "结束标签 {{{{{}}}}} 与 {{{{#{} {}}}}} 不匹配",
+                    tag, block.tag, block.name
+                )));
+            }
+            let node = Node::If {
+                name: block.name,
+                negate: block.tag == "unless",
+                then: block.then,
+                otherwise: block.otherwise.unwrap_or_default(),
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.nodes().push(node),
+                None => root.push(node),
+            }
+        } else {
+            let name = parse_name(tag)?;
+            match stack.last_mut() {
+                Some(block) => block.nodes().push(Node::Var(name)),
+                None => root.push(Node::Var(name)),
+            }
+        }
+    }
+
+    if let Some(block) = stack.last() {
+        return Err(TemplateError(format!(
+            "{{{{#{} {}}}}} 缺少结束标签",
+            block.tag, block.name
+        )));
+    }
+    if !rest.is_empty() {
+        root.push(Node::Text(rest.to_string()));
+    }
+
+    Ok(root)
+}
+
+fn parse_name(name: &str) -> Result<String, TemplateError> {
+    let name = name.trim();
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+    if !valid {
+        return Err(TemplateError(format!("无效的变量名: {}", name)));
+    }
+    Ok(name.to_string())
+}
+
+fn render_nodes(nodes: &[Node], vars: &HashMap<String, String>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Var(name) => {
+                if let Some(value) = vars.get(name) {
+                    output.push_str(value);
+                }
+            }
+            Node::If {
+                name,
+                negate,
+                then,
+                otherwise,
+            } => {
+                let truthy = vars.get(name).is_some_and(|v| !v.trim().is_empty());
+                if truthy != *negate {
+                    render_nodes(then, vars, output);
+                } else {
+                    render_nodes(otherwise, vars, output);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn student(content: &str, prompt: &str) -> student::Model {
+        student::Model {
+            id: 1,
+            name: "优香".to_string(),
+            avatars: "[]".to_string(),
+            prompt: prompt.to_string(),
+            is_custom: false,
+            metadata: "{}".to_string(),
+            content: content.to_string(),
+            prompt_template_id: None,
+            avatar_labels: "{}".to_string(),
+            typing_speed: None,
+        }
+    }
+
+    #[test]
+    fn renders_nested_branches() {
+        let template =
+            "{{#if a}}A{{#if b}}B{{else}}!B{{/if}}{{else}}!A{{#unless b}}!B{{/unless}}{{/if}}";
+
+        assert_eq!(
+            render(template, &vars(&[("a", "1"), ("b", "1")])).unwrap(),
+            "AB"
+        );
+        assert_eq!(render(template, &vars(&[("a", "1")])).unwrap(), "A!B");
+        assert_eq!(render(template, &vars(&[("b", "1")])).unwrap(), "!A");
+        assert_eq!(render(template, &vars(&[("b", " ")])).unwrap(), "!A!B");
+        assert_eq!(
+            render(
+                "{{#unless a}}无{{else}}有{{a}}{{/unless}}",
+                &vars(&[("a", "1")])
+            )
+            .unwrap(),
+            "有1"
+        );
+    }
+
+    #[test]
+    fn unknown_variables_render_empty() {
+        assert_eq!(
+            render("你好{{ name }}{{missing}}。", &vars(&[("name", "老师")])).unwrap(),
+            "你好老师。"
+        );
+        assert_eq!(
+            render("{{#if missing}}有{{/if}}", &HashMap::new()).unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for template in [
+            "{{name",
+            "{{#if a}}没有结束",
+            "{{#if a}}{{/unless}}",
+            "{{/if}}",
+            "{{else}}",
+            "{{#if a}}{{else}}{{else}}{{/if}}",
+            "{{#if }}{{/if}}",
+            "{{na me}}",
+        ] {
+            assert!(validate(template).is_err(), "{}", template);
+            assert!(
+                matches!(render(template, &HashMap::new()), Err(TemplateError(_))),
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn renders_default_template_without_profile() {
+        let today = NaiveDate::from_ymd_opt(2025, 5, 20).unwrap();
+
+        let output =
+            render_for_student(DEFAULT_TEMPLATE, &student("", ""), None, "", "老师", today)
+                .unwrap();
+        assert!(output.starts_with("请以优香的视角回答问题。\n\n请记住以下几点:"));
+        assert!(output.contains("就像老师真的在和优香对话"));
+        assert!(output.ends_with("6. 今天是2025年05月20日"));
+        assert!(!output.contains("{{"));
+
+        let output = render_for_student(
+            DEFAULT_TEMPLATE,
+            &student("档案", "补充设定"),
+            None,
+            "检索到的资料",
+            DEFAULT_USER_NAME,
+            today,
+        )
+        .unwrap();
+        assert!(output.starts_with(
+            "基于以下检索到的关于优香的信息，请以优香的视角回答问题:\n检索到的资料\n"
+        ));
+        assert!(output.contains("\n补充设定\n"));
+    }
+}
@@ -0,0 +1,73 @@
+use entity::message;
+
+// 启用向量检索时发送给模型的最近消息数量，更早的消息通过检索补充
+pub const HISTORY_WINDOW: usize = 40;
+
+// 每次检索的过往对话数量
+pub const MAX_RETRIEVED_EXCHANGES: usize = 3;
+
+// 检索结果的最低相似度
+pub const MIN_SIMILARITY: f32 = 0.45;
+
+// 一轮对话用于向量化的文本
+pub fn exchange_text(user_message: &str, reply: &str) -> String {
+    format!("用户：{}\n回复：{}", user_message, reply)
+}
+
+// 将检索到的回复连同之前的用户消息整理为prompt中的文本，按时间顺序排列
+//
+// 拆分为多条消息的回复，向量保存在最后一条消息上
+pub fn format_exchanges(
+    messages: &[message::Model],
+    reply_ids: &[i32],
+    student_name: &str,
+) -> String {
+    let mut positions: Vec<usize> = reply_ids
+        .iter()
+        .filter_map(|id| messages.iter().position(|m| m.id == *id))
+        .collect();
+    positions.sort_unstable();
+
+    positions
+        .into_iter()
+        .map(|position| {
+            messages[exchange_start(messages, position)..=position]
+                .iter()
+                .map(|m| match m.role.as_str() {
+                    "user" => format!("用户：{}", m.content),
+                    _ => format!("{}：{}", student_name, m.content),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// 标记能够通过检索找回的消息，即回复已有向量的轮次中的消息
+//
+// 开启向量检索之前的消息没有向量，不能从发送的历史中去掉
+pub fn retrievable(messages: &[message::Model], embedded_ids: &[i32]) -> Vec<bool> {
+    let mut retrievable = vec![false; messages.len()];
+    for (position, message) in messages.iter().enumerate() {
+        if embedded_ids.contains(&message.id) {
+            retrievable[exchange_start(messages, position)..=position].fill(true);
+        }
+    }
+    retrievable
+}
+
+// 回复所在轮次的起始位置，包括回复之前连续的用户消息
+fn exchange_start(messages: &[message::Model], position: usize) -> usize {
+    // 同一轮次拆分出的多条消息属于同一次回复
+    let turn_id = &messages[position].turn_id;
+    let reply_start = messages[..position]
+        .iter()
+        .rposition(|m| turn_id.is_none() || m.turn_id != *turn_id)
+        .map_or(0, |i| i + 1);
+
+    messages[..reply_start]
+        .iter()
+        .rposition(|m| m.role != "user")
+        .map_or(0, |i| i + 1)
+}
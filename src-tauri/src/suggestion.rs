@@ -0,0 +1,68 @@
+use serde::Deserialize;
+
+// 候选回复数量的范围
+pub const MIN_SUGGESTIONS: usize = 2;
+pub const MAX_SUGGESTIONS: usize = 4;
+
+// 单条候选回复的最大字数，超出的部分截断
+const MAX_SUGGESTION_CHARS: usize = 40;
+
+// 生成候选回复时参考的最近消息数量
+pub const CONTEXT_MESSAGES: usize = 8;
+
+#[derive(Debug, Deserialize)]
+struct Suggestions {
+    #[serde(default)]
+    replies: Vec<String>,
+}
+
+// 让LLM为用户生成候选回复的提示词，要求输出JSON
+//
+// dialogue为按时间顺序排列的(角色, 内容)
+pub fn suggestion_prompt(student_name: &str, dialogue: &[(&str, &str)]) -> String {
+    let dialogue = dialogue
+        .iter()
+        .map(|(role, content)| match *role {
+            "user" => format!("用户：{}", content),
+            _ => format!("{}：{}", student_name, content),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "下面是用户与{name}在MomoTalk上的聊天记录。请站在用户的角度，为用户的下一条消息提供\
+{min}到{max}个简短的候选回复，语气自然口语化，彼此之间在态度或话题上有所区别，每条不超过{chars}字。\n\n\
+聊天记录：\n{dialogue}\n\n\
+只输出JSON，不要输出其他内容，格式为：{{\"replies\": [\"候选回复1\", \"候选回复2\"]}}",
+        name = student_name,
+        min = MIN_SUGGESTIONS,
+        max = MAX_SUGGESTIONS,
+        chars = MAX_SUGGESTION_CHARS,
+        dialogue = dialogue,
+    )
+}
+
+// 解析LLM输出的JSON，兼容包裹在代码块中的输出，去掉空白与重复的候选
+//
+// 剩余的候选少于MIN_SUGGESTIONS时返回空列表，不展示候选回复
+pub fn parse_suggestions(output: &str) -> Result<Vec<String>, serde_json::Error> {
+    let json = match (output.find('{'), output.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &output[start..=end],
+        _ => output,
+    };
+    let suggestions: Suggestions = serde_json::from_str(json)?;
+
+    let mut replies: Vec<String> = Vec::new();
+    for reply in suggestions.replies {
+        let reply: String = reply.trim().chars().take(MAX_SUGGESTION_CHARS).collect();
+        if !reply.is_empty() && !replies.contains(&reply) {
+            replies.push(reply);
+        }
+    }
+    if replies.len() < MIN_SUGGESTIONS {
+        return Ok(Vec::new());
+    }
+    replies.truncate(MAX_SUGGESTIONS);
+
+    Ok(replies)
+}
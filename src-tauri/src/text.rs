@@ -0,0 +1,86 @@
+// 将文本切分为检索用的词元
+//
+// 中文没有空格分词，按相邻两个汉字切分（单个汉字成词时保留单字）；
+// 英文与数字按连续字符切分并转为小写。
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut cjk_run: Vec<char> = Vec::new();
+    let mut word = String::new();
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush_word(&mut word, &mut tokens);
+            cjk_run.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk_run, &mut tokens);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut tokens);
+            flush_cjk(&mut cjk_run, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    flush_cjk(&mut cjk_run, &mut tokens);
+
+    tokens
+}
+
+// 粗略估算文本的token数：汉字与假名按每字一个token，其余字符按每四个一个token
+pub fn estimate_tokens(text: &str) -> usize {
+    let (cjk, other) =
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .fold((0usize, 0usize), |(cjk, other), c| {
+                if is_cjk(c) {
+                    (cjk + 1, other)
+                } else {
+                    (cjk, other + 1)
+                }
+            });
+
+    cjk + other.div_ceil(4)
+}
+
+fn flush_word(word: &mut String, tokens: &mut Vec<String>) {
+    if !word.is_empty() {
+        tokens.push(std::mem::take(word));
+    }
+}
+
+fn flush_cjk(run: &mut Vec<char>, tokens: &mut Vec<String>) {
+    match run.len() {
+        0 => {}
+        1 => tokens.push(run[0].to_string()),
+        _ => tokens.extend(run.windows(2).map(|pair| pair.iter().collect::<String>())),
+    }
+    run.clear();
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{F900}'..='\u{FAFF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_mixed_text() {
+        assert_eq!(
+            tokenize("优香的MomoTalk ID是Yuuka01！喜欢计算"),
+            ["优香", "香的", "momotalk", "id", "是", "yuuka01", "喜欢", "欢计", "计算"]
+        );
+        assert_eq!(tokenize("猫 cat、ねこ"), ["猫", "cat", "ねこ"]);
+        assert!(tokenize(" ，。!? ").is_empty());
+    }
+
+    #[test]
+    fn estimates_tokens() {
+        assert_eq!(estimate_tokens("优香 hello"), 4);
+        assert_eq!(estimate_tokens(""), 0);
+    }
+}
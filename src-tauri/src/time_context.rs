@@ -0,0 +1,399 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeDelta, Timelike, Weekday};
+
+// 距离上一条消息的间隔不足此值时不提示
+const MIN_GAP_MINUTES: i64 = 60;
+
+// 生日在此天数内时提示即将到来
+const UPCOMING_BIRTHDAY_DAYS: i64 = 7;
+
+// 提供当前时间，便于替换为固定时间进行测试
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<FixedOffset>;
+}
+
+// 使用系统本地时间
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        chrono::Local::now().fixed_offset()
+    }
+}
+
+// 注入内容使用的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    Zh,
+    Ja,
+    En,
+}
+
+impl Locale {
+    // 按语言标签选择，例如zh-CN、ja、en-US，无法识别时使用中文
+    pub fn from_tag(tag: &str) -> Self {
+        let tag = tag.trim().to_lowercase();
+        if tag.starts_with("ja") {
+            Locale::Ja
+        } else if tag.starts_with("en") {
+            Locale::En
+        } else {
+            Locale::Zh
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOfDay {
+    EarlyMorning,
+    Morning,
+    Noon,
+    Afternoon,
+    Evening,
+    Night,
+    LateNight,
+}
+
+impl TimeOfDay {
+    pub fn from_hour(hour: u32) -> Self {
+        match hour {
+            5..=7 => TimeOfDay::EarlyMorning,
+            8..=10 => TimeOfDay::Morning,
+            11..=12 => TimeOfDay::Noon,
+            13..=16 => TimeOfDay::Afternoon,
+            17..=18 => TimeOfDay::Evening,
+            19..=22 => TimeOfDay::Night,
+            _ => TimeOfDay::LateNight,
+        }
+    }
+
+    fn label(self, locale: Locale) -> &'static str {
+        let labels = match locale {
+            Locale::Zh => ["清晨", "上午", "中午", "下午", "傍晚", "晚上", "深夜"],
+            Locale::Ja => ["早朝", "午前", "昼", "午後", "夕方", "夜", "深夜"],
+            Locale::En => [
+                "early morning",
+                "morning",
+                "noon",
+                "afternoon",
+                "evening",
+                "night",
+                "late night",
+            ],
+        };
+        labels[self as usize]
+    }
+}
+
+// 时间段落的配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeContextConfig {
+    pub enabled: bool,
+    pub locale: Locale,
+}
+
+// 生成时间段落所需的信息
+pub struct TimeContext<'a> {
+    pub student_name: &'a str,
+    // 学生生日的月与日
+    pub birthday: Option<(u32, u32)>,
+    // 上一条消息的时间
+    pub last_message_at: Option<DateTime<FixedOffset>>,
+}
+
+// 描述当前的日期、星期、时段、距上一条消息的间隔以及学生的生日
+pub fn describe(clock: &dyn Clock, locale: Locale, context: &TimeContext) -> String {
+    let now = clock.now();
+    let mut lines = vec![current_time(now, locale)];
+
+    if let Some(last_message_at) = context.last_message_at {
+        if let Some(line) = elapsed_since(now - last_message_at, locale) {
+            lines.push(line);
+        }
+    }
+
+    if let Some((month, day)) = context.birthday {
+        if let Some(line) = birthday(now.date_naive(), month, day, context.student_name, locale) {
+            lines.push(line);
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn current_time(now: DateTime<FixedOffset>, locale: Locale) -> String {
+    let time_of_day = TimeOfDay::from_hour(now.hour()).label(locale);
+    let clock_time = now.format("%H:%M");
+
+    match locale {
+        Locale::Zh => format!(
+            "现在是{}年{}月{}日 {} {} {}。",
+            now.year(),
+            now.month(),
+            now.day(),
+            weekday(now.weekday(), locale),
+            time_of_day,
+            clock_time
+        ),
+        Locale::Ja => format!(
+            "現在は{}年{}月{}日（{}）{} {}です。",
+            now.year(),
+            now.month(),
+            now.day(),
+            weekday(now.weekday(), locale),
+            time_of_day,
+            clock_time
+        ),
+        Locale::En => format!(
+            "It is now {}, {} ({}), {}.",
+            weekday(now.weekday(), locale),
+            now.format("%B %-d, %Y"),
+            time_of_day,
+            clock_time
+        ),
+    }
+}
+
+fn weekday(weekday: Weekday, locale: Locale) -> &'static str {
+    let names = match locale {
+        Locale::Zh => [
+            "星期一",
+            "星期二",
+            "星期三",
+            "星期四",
+            "星期五",
+            "星期六",
+            "星期日",
+        ],
+        Locale::Ja => [
+            "月曜日",
+            "火曜日",
+            "水曜日",
+            "木曜日",
+            "金曜日",
+            "土曜日",
+            "日曜日",
+        ],
+        Locale::En => [
+            "Monday",
+            "Tuesday",
+            "Wednesday",
+            "Thursday",
+            "Friday",
+            "Saturday",
+            "Sunday",
+        ],
+    };
+    names[weekday.num_days_from_monday() as usize]
+}
+
+fn elapsed_since(gap: TimeDelta, locale: Locale) -> Option<String> {
+    let minutes = gap.num_minutes();
+    if minutes < MIN_GAP_MINUTES {
+        return None;
+    }
+
+    let (days, hours) = (gap.num_days(), gap.num_hours());
+    let duration = match locale {
+        Locale::Zh if days > 0 => format!("{}天", days),
+        Locale::Zh => format!("{}小时", hours),
+        Locale::Ja if days > 0 => format!("{}日", days),
+        Locale::Ja => format!("{}時間", hours),
+        Locale::En if days > 0 => plural(days, "day"),
+        Locale::En => plural(hours, "hour"),
+    };
+
+    Some(match (locale, days > 0) {
+        (Locale::Zh, true) => format!(
+            "距离用户上一条消息已经过去了{}，可以自然地回应这段时间的空白。",
+            duration
+        ),
+        (Locale::Zh, false) => format!("距离用户上一条消息已经过去了{}。", duration),
+        (Locale::Ja, true) => format!(
+            "ユーザーの前回のメッセージから{}が経過しています。久しぶりであることに自然に触れても構いません。",
+            duration
+        ),
+        (Locale::Ja, false) => {
+            format!("ユーザーの前回のメッセージから{}が経過しています。", duration)
+        }
+        (Locale::En, true) => format!(
+            "It has been {} since the user's last message; it is natural to acknowledge the absence.",
+            duration
+        ),
+        (Locale::En, false) => format!("It has been {} since the user's last message.", duration),
+    })
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+// 生日临近时提示，其余时间只说明生日的日期；日期无效时返回None
+//
+// 生日当天由"今天的特别日子"段落说明，这里不再重复
+fn birthday(
+    today: NaiveDate,
+    month: u32,
+    day: u32,
+    student_name: &str,
+    locale: Locale,
+) -> Option<String> {
+    let days_until = days_until_birthday(today, month, day)?;
+    if days_until == 0 {
+        return None;
+    }
+
+    Some(match (locale, days_until) {
+        (Locale::Zh, n) if n <= UPCOMING_BIRTHDAY_DAYS => format!(
+            "{}的生日（{}月{}日）快到了，还有{}天。",
+            student_name, month, day, n
+        ),
+        (Locale::Zh, _) => format!("{}的生日是{}月{}日。", student_name, month, day),
+        (Locale::Ja, n) if n <= UPCOMING_BIRTHDAY_DAYS => format!(
+            "{}の誕生日（{}月{}日）まであと{}日です。",
+            student_name, month, day, n
+        ),
+        (Locale::Ja, _) => format!("{}の誕生日は{}月{}日です。", student_name, month, day),
+        (Locale::En, n) if n <= UPCOMING_BIRTHDAY_DAYS => format!(
+            "{}'s birthday ({}) is in {}.",
+            student_name,
+            month_day(month, day)?,
+            plural(n, "day")
+        ),
+        (Locale::En, _) => format!(
+            "{}'s birthday is on {}.",
+            student_name,
+            month_day(month, day)?
+        ),
+    })
+}
+
+// 距离下一个生日的天数，2月29日的生日在平年按3月1日计算
+pub fn days_until_birthday(today: NaiveDate, month: u32, day: u32) -> Option<i64> {
+    let on_year = |year: i32| {
+        NaiveDate::from_ymd_opt(year, month, day).or_else(|| {
+            (month == 2 && day == 29)
+                .then(|| NaiveDate::from_ymd_opt(year, 3, 1))
+                .flatten()
+        })
+    };
+
+    let this_year = on_year(today.year())?;
+    let next = if this_year >= today {
+        this_year
+    } else {
+        on_year(today.year() + 1)?
+    };
+
+    Some((next - today).num_days())
+}
+
+fn month_day(month: u32, day: u32) -> Option<String> {
+    NaiveDate::from_ymd_opt(2000, month, day).map(|date| date.format("%B %-d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    struct FixedClock(DateTime<FixedOffset>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> DateTime<FixedOffset> {
+            self.0
+        }
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn describe_uses_clock() {
+        let clock = FixedClock(at(2025, 5, 20, 21, 5));
+        let context = TimeContext {
+            student_name: "优香",
+            birthday: Some((5, 23)),
+            last_message_at: Some(at(2025, 5, 17, 20, 0)),
+        };
+
+        assert_eq!(
+            describe(&clock, Locale::Zh, &context),
+            "现在是2025年5月20日 星期二 晚上 21:05。\n\
+             距离用户上一条消息已经过去了3天，可以自然地回应这段时间的空白。\n\
+             优香的生日（5月23日）快到了，还有3天。"
+        );
+        assert_eq!(
+            describe(
+                &clock,
+                Locale::En,
+                &TimeContext {
+                    birthday: None,
+                    last_message_at: None,
+                    ..context
+                }
+            ),
+            "It is now Tuesday, May 20, 2025 (night), 21:05."
+        );
+    }
+
+    #[test]
+    fn describe_leaves_birthday_to_calendar() {
+        let clock = FixedClock(at(2025, 5, 23, 9, 0));
+        let context = TimeContext {
+            student_name: "优香",
+            birthday: Some((5, 23)),
+            last_message_at: None,
+        };
+
+        assert!(!describe(&clock, Locale::Zh, &context).contains("生日"));
+    }
+
+    #[test]
+    fn elapsed_since_skips_short_gaps() {
+        assert_eq!(elapsed_since(TimeDelta::minutes(59), Locale::Zh), None);
+        assert_eq!(
+            elapsed_since(TimeDelta::hours(5), Locale::Zh).as_deref(),
+            Some("距离用户上一条消息已经过去了5小时。")
+        );
+        assert_eq!(
+            elapsed_since(TimeDelta::hours(30), Locale::En).as_deref(),
+            Some(
+                "It has been 1 day since the user's last message; \
+                 it is natural to acknowledge the absence."
+            )
+        );
+    }
+
+    #[test]
+    fn days_until_birthday_rolls_over_year() {
+        assert_eq!(days_until_birthday(date(2025, 5, 20), 5, 20), Some(0));
+        assert_eq!(days_until_birthday(date(2025, 12, 31), 1, 1), Some(1));
+        assert_eq!(days_until_birthday(date(2025, 5, 21), 5, 20), Some(364));
+        assert_eq!(days_until_birthday(date(2025, 2, 30 - 2), 2, 30), None);
+    }
+
+    #[test]
+    fn days_until_birthday_on_feb_29() {
+        // 闰年当天
+        assert_eq!(days_until_birthday(date(2024, 2, 29), 2, 29), Some(0));
+        assert_eq!(days_until_birthday(date(2024, 2, 28), 2, 29), Some(1));
+        // 平年按3月1日计算
+        assert_eq!(days_until_birthday(date(2025, 2, 28), 2, 29), Some(1));
+        assert_eq!(days_until_birthday(date(2025, 3, 2), 2, 29), Some(364));
+        // 跨年到闰年
+        assert_eq!(days_until_birthday(date(2027, 3, 2), 2, 29), Some(364));
+    }
+}
@@ -0,0 +1,97 @@
+use entity::message;
+use serde::Serialize;
+
+// 对话标题更新事件，前端据此刷新聊天列表
+pub const CONVERSATION_TITLE_EVENT: &str = "conversation-title";
+
+// 只根据最初几轮对话生成标题，之后不再自动更新
+pub const TITLE_EXCHANGES: usize = 3;
+
+// 标题的最大字数，超出的部分截断
+const MAX_TITLE_CHARS: usize = 16;
+
+// 提示词中每条消息保留的最大字数
+const MAX_CONTENT_CHARS: usize = 200;
+
+// 标题两端需要去掉的引号、标记与标点
+const TITLE_MARKS: &[char] = &[
+    '"', '\'', '“', '”', '‘', '’', '「', '」', '『', '』', '《', '》', '*', '#', '`', '。', '.',
+    '！', '!', '？', '?', '，', ',',
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationTitleEvent {
+    pub conversation_id: String,
+    pub title: String,
+}
+
+// 已完成的对话轮数，一轮为连续的用户消息加上学生的回复，学生主动发起的消息不计入
+pub fn exchange_count(messages: &[message::Model]) -> usize {
+    messages
+        .windows(2)
+        .filter(|pair| pair[0].role == "user" && pair[1].role == "assistant")
+        .count()
+}
+
+// 前n轮对话的消息，在第n+1轮的用户消息之前截断
+pub fn first_exchanges(messages: &[message::Model], n: usize) -> &[message::Model] {
+    let mut exchanges = 0;
+    for (i, msg) in messages.iter().enumerate() {
+        let starts_exchange = msg.role == "user" && (i == 0 || messages[i - 1].role != "user");
+        if starts_exchange {
+            exchanges += 1;
+            if exchanges > n {
+                return &messages[..i];
+            }
+        }
+    }
+
+    messages
+}
+
+// 让LLM为对话生成标题的提示词
+//
+// dialogue为按时间顺序排列的(角色, 内容)
+pub fn title_prompt(student_name: &str, dialogue: &[(&str, &str)]) -> String {
+    let dialogue = dialogue
+        .iter()
+        .map(|(role, content)| {
+            let content: String = content.chars().take(MAX_CONTENT_CHARS).collect();
+            match *role {
+                "user" => format!("用户：{}", content),
+                _ => format!("{}：{}", student_name, content),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "下面是用户与{name}在MomoTalk上的聊天记录。请用一个不超过{chars}字的短语概括这段对话的话题，\
+作为对话的标题，不要包含{name}的名字。\n\n\
+聊天记录：\n{dialogue}\n\n\
+只输出标题，不要输出引号、标点或其他内容。",
+        name = student_name,
+        chars = MAX_TITLE_CHARS,
+        dialogue = dialogue,
+    )
+}
+
+// 整理LLM输出的标题：取第一个非空行，去掉前缀、引号与标点，超出长度时截断
+pub fn clean_title(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())?;
+    let line = ["标题：", "标题:", "Title:"]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))
+        .unwrap_or(line);
+
+    let title: String = line
+        .trim_matches(|c: char| TITLE_MARKS.contains(&c) || c.is_whitespace())
+        .chars()
+        .take(MAX_TITLE_CHARS)
+        .collect();
+
+    Some(title.trim_end().to_string()).filter(|title| !title.is_empty())
+}
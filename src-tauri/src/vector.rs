@@ -0,0 +1,47 @@
+// 向量按小端序f32保存为blob
+pub fn to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn from_blob(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+// 余弦相似度，维度不同或存在零向量时为0
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+// 暴力搜索与query最相似的k个向量，返回(来源ID, 相似度)，按相似度降序
+pub fn top_k(
+    query: &[f32],
+    candidates: impl IntoIterator<Item = (i32, Vec<f32>)>,
+    k: usize,
+    min_similarity: f32,
+) -> Vec<(i32, f32)> {
+    let mut scored: Vec<(i32, f32)> = candidates
+        .into_iter()
+        .map(|(id, vector)| (id, cosine_similarity(query, &vector)))
+        .filter(|(_, similarity)| *similarity >= min_similarity)
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scored.truncate(k);
+
+    scored
+}
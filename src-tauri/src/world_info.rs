@@ -0,0 +1,171 @@
+use crate::text;
+use entity::{lorebook, lorebook_entry};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+// 世界书的作用范围
+pub const SCOPE_GLOBAL: &str = "global";
+pub const SCOPE_SCHOOL: &str = "school";
+pub const SCOPE_STUDENT: &str = "student";
+
+// 词条的插入位置
+pub const POSITION_BEFORE: &str = "before"; // 学生设定之前
+pub const POSITION_AFTER: &str = "after"; // 学生设定之后
+
+// 新建世界书的默认设置
+pub const DEFAULT_SCAN_DEPTH: i32 = 4;
+pub const DEFAULT_TOKEN_BUDGET: i32 = 1024;
+
+// 被触发的词条，供注入prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggeredEntry {
+    pub lorebook_id: i32,
+    pub entry_id: i32,
+    pub title: String,
+    pub position: String,
+    pub priority: i32,
+    pub tokens: usize,
+    pub content: String,
+    // 命中的关键词，始终注入的词条为None
+    pub keyword: Option<String>,
+}
+
+// 检查作用范围与对应的学校、学生是否匹配
+pub fn validate_scope(
+    scope: &str,
+    school: Option<&str>,
+    student_id: Option<i32>,
+) -> Result<(), String> {
+    match scope {
+        SCOPE_GLOBAL => Ok(()),
+        SCOPE_SCHOOL if school.is_some_and(|s| !s.trim().is_empty()) => Ok(()),
+        SCOPE_SCHOOL => Err("School is required for school scoped lorebooks".to_string()),
+        SCOPE_STUDENT if student_id.is_some() => Ok(()),
+        SCOPE_STUDENT => Err("Student is required for student scoped lorebooks".to_string()),
+        _ => Err(format!("Invalid lorebook scope: {}", scope)),
+    }
+}
+
+// 检查词条的插入位置与关键词，正则表达式无法解析时返回错误
+pub fn validate_entry(keywords: &[String], use_regex: bool, position: &str) -> Result<(), String> {
+    if position != POSITION_BEFORE && position != POSITION_AFTER {
+        return Err(format!("Invalid entry position: {}", position));
+    }
+
+    if use_regex {
+        for keyword in keywords {
+            build_regex(keyword).map_err(|e| format!("无效的正则表达式 {}: {}", keyword, e))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn parse_keywords(keywords: &str) -> Vec<String> {
+    serde_json::from_str(keywords).unwrap_or_default()
+}
+
+fn build_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+// 返回词条在文本中命中的第一个关键词，普通关键词不区分大小写
+pub fn match_entry(entry: &lorebook_entry::Model, text: &str) -> Option<String> {
+    let lowered = text.to_lowercase();
+
+    parse_keywords(&entry.keywords)
+        .into_iter()
+        .filter(|keyword| !keyword.trim().is_empty())
+        .find(|keyword| {
+            if entry.use_regex {
+                build_regex(keyword).is_ok_and(|re| re.is_match(text))
+            } else {
+                lowered.contains(&keyword.trim().to_lowercase())
+            }
+        })
+}
+
+// 世界书是否作用于指定学生，学校按包含关系匹配，例如「格黑娜」匹配「格黑娜学园」
+pub fn applies_to(book: &lorebook::Model, student_id: i32, school: Option<&str>) -> bool {
+    match book.scope.as_str() {
+        SCOPE_GLOBAL => true,
+        SCOPE_SCHOOL => match (book.school.as_deref().map(str::trim), school) {
+            (Some(book_school), Some(school)) => {
+                !book_school.is_empty() && school.contains(book_school)
+            }
+            _ => false,
+        },
+        SCOPE_STUDENT => book.student_id == Some(student_id),
+        _ => false,
+    }
+}
+
+// 扫描最近的消息，挑选触发的词条
+//
+// recent_messages按时间顺序排列，每本世界书只扫描最后scan_depth条；
+// 触发的词条按优先级从高到低加入，超出该世界书token预算的词条会被跳过
+pub fn select_entries(
+    books: &[(lorebook::Model, Vec<lorebook_entry::Model>)],
+    recent_messages: &[&str],
+) -> Vec<TriggeredEntry> {
+    let mut triggered = Vec::new();
+
+    for (book, entries) in books {
+        let depth = usize::try_from(book.scan_depth).unwrap_or(0);
+        let scan_text = recent_messages[recent_messages.len().saturating_sub(depth)..].join("\n");
+
+        let mut matched: Vec<TriggeredEntry> = entries
+            .iter()
+            .filter(|entry| entry.enabled && !entry.content.trim().is_empty())
+            .filter_map(|entry| {
+                let keyword = if entry.constant {
+                    None
+                } else {
+                    Some(match_entry(entry, &scan_text)?)
+                };
+                Some(TriggeredEntry {
+                    lorebook_id: book.id,
+                    entry_id: entry.id,
+                    title: entry.title.clone(),
+                    position: entry.position.clone(),
+                    priority: entry.priority,
+                    tokens: text::estimate_tokens(&entry.content),
+                    content: entry.content.trim().to_string(),
+                    keyword,
+                })
+            })
+            .collect();
+        sort_by_priority(&mut matched);
+
+        let budget = usize::try_from(book.token_budget).unwrap_or(0);
+        let mut used = 0;
+        for entry in matched {
+            if used + entry.tokens > budget {
+                continue;
+            }
+            used += entry.tokens;
+            triggered.push(entry);
+        }
+    }
+    sort_by_priority(&mut triggered);
+
+    triggered
+}
+
+fn sort_by_priority(entries: &mut [TriggeredEntry]) {
+    entries.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then(a.entry_id.cmp(&b.entry_id))
+    });
+}
+
+// 指定插入位置的词条内容
+pub fn format_entries(entries: &[TriggeredEntry], position: &str) -> String {
+    entries
+        .iter()
+        .filter(|entry| entry.position == position)
+        .map(|entry| entry.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -1,2 +1,3 @@
 export { default as AppNavbar } from './AppNavbar.vue'
 export { default as AppSidebar } from './AppSidebar.vue'
+export { default as StartupBanner } from './StartupBanner.vue'
@@ -1,4 +1,5 @@
 import type { Updater } from '@tanstack/vue-table'
+import { convertFileSrc } from '@tauri-apps/api/core'
 import type { Ref } from 'vue'
 import { type ClassValue, clsx } from 'clsx'
 import { twMerge } from 'tailwind-merge'
@@ -13,3 +14,8 @@ export function valueUpdater<T extends Updater<any>>(updaterOrValue: T, ref: Ref
       ? updaterOrValue(ref.value)
       : updaterOrValue
 }
+
+// 通过后端的 avatar 协议加载头像，离线时使用本地缓存
+export function avatarSrc(src: string) {
+  return src ? convertFileSrc(src, 'avatar') : ''
+}
@@ -0,0 +1,8 @@
+export interface BondStatus {
+  student_id: number;
+  points: number;
+  level: number;
+  stage: string;
+  level_points: number;
+  next_level_points: number | null;
+}
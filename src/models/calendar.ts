@@ -0,0 +1,36 @@
+export type CalendarEventKind = 'birthday' | 'first_chat' | 'anniversary';
+
+export interface CalendarEvent {
+  kind: CalendarEventKind;
+  title: string;
+  student_id: number | null;
+  student_name: string | null;
+  // YYYY-MM-DD
+  date: string;
+  days_until: number;
+  // 第几周年
+  years: number | null;
+  prompt: string | null;
+  anniversary_id: number | null;
+}
+
+export interface Anniversary {
+  id: number;
+  student_id: number | null;
+  title: string;
+  month: number;
+  day: number;
+  year: number | null;
+  prompt: string | null;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface AnniversaryData {
+  student_id?: number | null;
+  title: string;
+  month: number;
+  day: number;
+  year?: number | null;
+  prompt?: string | null;
+}
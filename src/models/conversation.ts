@@ -20,7 +20,10 @@ export interface ConversationWithStudent {
   createdAt: string;
   updatedAt: string;
   title: string | null;
+  // 为true时标题由用户设置，不会被自动生成的标题覆盖
+  title_locked?: boolean;
   student_name: string;
+  avatar?: string | null;
   student?: Student;
   lastMessage?: string
 }
\ No newline at end of file
@@ -0,0 +1,7 @@
+export interface LorePassage {
+  chunk_id: number;
+  position: number;
+  heading: string;
+  content: string;
+  score: number;
+}
@@ -0,0 +1,51 @@
+export type LorebookScope = 'global' | 'school' | 'student';
+
+export type LorebookEntryPosition = 'before' | 'after';
+
+export interface Lorebook {
+  id: number;
+  name: string;
+  scope: LorebookScope;
+  school: string | null;
+  student_id: number | null;
+  scan_depth: number;
+  token_budget: number;
+  enabled: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface LorebookData {
+  name: string;
+  scope: LorebookScope;
+  school?: string | null;
+  student_id?: number | null;
+  scan_depth?: number;
+  token_budget?: number;
+}
+
+export interface LorebookEntry {
+  id: number;
+  lorebook_id: number;
+  title: string;
+  // JSON数组
+  keywords: string;
+  use_regex: boolean;
+  content: string;
+  position: LorebookEntryPosition;
+  priority: number;
+  constant: boolean;
+  enabled: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface LorebookEntryData {
+  title?: string;
+  keywords: string[];
+  use_regex?: boolean;
+  content: string;
+  position?: LorebookEntryPosition;
+  priority?: number;
+  constant?: boolean;
+}
@@ -0,0 +1,9 @@
+export interface StudentMemory {
+  id: number;
+  student_id: number;
+  content: string;
+  importance: number;
+  created_at: string;
+  updated_at: string;
+  last_used_at: string | null;
+}
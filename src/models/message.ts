@@ -1,4 +1,22 @@
 export interface Message {
+  // 已保存的消息才有ID
+  id?: number
   role: 'user' | 'assistant' | 'system' | 'tool'
   content: string
+  // 用户消息被学生读到的时间
+  read_at?: string | null
+  // 学生消息送达的时间
+  delivered_at?: string | null
+}
+
+// chat_with_llm的返回值，bubbles为拆分后的多条消息
+export interface ChatReply {
+  role: 'assistant'
+  content: string
+  turn_id: string
+  bubbles: string[]
+  // 为true时消息通过message-delivered事件逐条送达
+  staggered: boolean
+  // 用户下一条消息的候选回复，未开启快捷回复时为空
+  suggestions: string[]
 }
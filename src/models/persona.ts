@@ -0,0 +1,17 @@
+export interface Persona {
+  id: number;
+  name: string;
+  address: string;
+  bio: string | null;
+  preferences: string | null;
+  is_active: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PersonaData {
+  name: string;
+  address: string;
+  bio?: string | null;
+  preferences?: string | null;
+}
@@ -0,0 +1,23 @@
+// 数据集中单条记录的问题
+export interface DatasetIssue {
+  file: string;
+  index: number;
+  name: string | null;
+  message: string;
+}
+
+export interface DatasetSyncReport {
+  previous_version: string | null;
+  current_version: string;
+  added: string[];
+  updated: string[];
+  skipped: string[];
+  issues: DatasetIssue[];
+}
+
+// 启动状态，degraded为true时数据库不可用
+export interface StartupStatus {
+  degraded: boolean;
+  errors: string[];
+  dataset: DatasetSyncReport | null;
+}
@@ -3,13 +3,34 @@ import type {
   Conversation,
   ConversationWithStudent,
 } from '~/models/conversation'
-import type { Message } from '~/models/message'
+import type { BondStatus } from '~/models/bond'
+import type {
+  Anniversary,
+  AnniversaryData,
+  CalendarEvent,
+} from '~/models/calendar'
+import type { LorePassage } from '~/models/lore'
+import type {
+  Lorebook,
+  LorebookData,
+  LorebookEntry,
+  LorebookEntryData,
+} from '~/models/lorebook'
+import type { StudentMemory } from '~/models/memory'
+import type { ChatReply, Message } from '~/models/message'
+import type { Persona, PersonaData } from '~/models/persona'
+import type { StartupStatus } from '~/models/startup'
 
 export class TauriService {
   async setStore(key: string, value: any): Promise<void> {
     await invoke('set_store', { key, value })
   }
 
+  // 启动状态，包括数据集同步结果与降级原因
+  async getStartupStatus(): Promise<StartupStatus> {
+    return await invoke<StartupStatus>('get_startup_status')
+  }
+
   async getConversations(): Promise<ConversationWithStudent[]> {
     const conversations = await invoke<ConversationWithStudent[]>(
       'get_conversations'
@@ -43,10 +64,20 @@ export class TauriService {
     return await invoke<Conversation>('update_conversation', { id, data })
   }
 
+  // 根据最初几轮对话重新生成标题，会覆盖并解锁用户设置的标题
+  async generateConversationTitle(id: string): Promise<Conversation> {
+    return await invoke<Conversation>('generate_conversation_title', { id })
+  }
+
   async deleteConversation(id: string): Promise<Conversation> {
     return await invoke<Conversation>('delete_conversation', { id })
   }
 
+  // 标记对话为已读，已读位置移动到最后一条已送达的消息
+  async markConversationRead(id: string): Promise<Conversation> {
+    return await invoke<Conversation>('mark_conversation_read', { id })
+  }
+
   // 添加消息相关方法
   async getMessagesByConversationId(
     conversationId: string
@@ -56,17 +87,18 @@ export class TauriService {
     })
   }
 
-  async getMessagesByConversationIdWithPagination(
+  async getMessagesByConversationIdWithCursor(
     conversationId: string,
-    page: number,
-    pageSize: number
-  ): Promise<Message[]> {
-    return await invoke<Message[]>(
-      'get_messages_by_conversation_id_with_pagination',
+    limit: number,
+    cursor: { beforeIndex?: number; afterIndex?: number } = {}
+  ): Promise<{ messages: Message[]; has_more: boolean }> {
+    return await invoke<{ messages: Message[]; has_more: boolean }>(
+      'get_messages_by_conversation_id_with_cursor',
       {
         conversationId,
-        page,
-        pageSize,
+        beforeIndex: cursor.beforeIndex ?? null,
+        afterIndex: cursor.afterIndex ?? null,
+        limit,
       }
     )
   }
@@ -85,12 +117,199 @@ export class TauriService {
   async chatWithLLM(
     message: Message,
     conversationId: string
-  ): Promise<Message> {
-    return await invoke<Message>('chat_with_llm', {
+  ): Promise<ChatReply> {
+    return await invoke<ChatReply>('chat_with_llm', {
       message,
       conversationId: conversationId,
     })
   }
+
+  // 根据最近的聊天记录生成候选回复，不保存到历史
+  async suggestReplies(conversationId: string): Promise<string[]> {
+    return await invoke<string[]>('suggest_replies', {
+      conversationId: conversationId,
+    })
+  }
+
+  async getStudentBond(studentId: number): Promise<BondStatus> {
+    return await invoke<BondStatus>('get_student_bond', { studentId })
+  }
+
+  async getStudentBonds(): Promise<BondStatus[]> {
+    return await invoke<BondStatus[]>('get_student_bonds')
+  }
+
+  async getStudentMemories(studentId: number): Promise<StudentMemory[]> {
+    return await invoke<StudentMemory[]>('get_student_memories', { studentId })
+  }
+
+  async createStudentMemory(
+    studentId: number,
+    data: { content: string; importance?: number }
+  ): Promise<StudentMemory> {
+    return await invoke<StudentMemory>('create_student_memory', {
+      studentId,
+      data,
+    })
+  }
+
+  async updateStudentMemory(
+    id: number,
+    data: { content?: string; importance?: number }
+  ): Promise<StudentMemory> {
+    return await invoke<StudentMemory>('update_student_memory', { id, data })
+  }
+
+  async deleteStudentMemory(id: number): Promise<StudentMemory> {
+    return await invoke<StudentMemory>('delete_student_memory', { id })
+  }
+
+  async getLorebooks(): Promise<Lorebook[]> {
+    return await invoke<Lorebook[]>('get_lorebooks')
+  }
+
+  async createLorebook(data: LorebookData): Promise<Lorebook> {
+    return await invoke<Lorebook>('create_lorebook', { data })
+  }
+
+  async updateLorebook(
+    id: number,
+    data: Partial<LorebookData> & { enabled?: boolean }
+  ): Promise<Lorebook> {
+    return await invoke<Lorebook>('update_lorebook', { id, data })
+  }
+
+  async deleteLorebook(id: number): Promise<Lorebook> {
+    return await invoke<Lorebook>('delete_lorebook', { id })
+  }
+
+  async getLorebookEntries(lorebookId: number): Promise<LorebookEntry[]> {
+    return await invoke<LorebookEntry[]>('get_lorebook_entries', {
+      lorebookId,
+    })
+  }
+
+  async createLorebookEntry(
+    lorebookId: number,
+    data: LorebookEntryData
+  ): Promise<LorebookEntry> {
+    return await invoke<LorebookEntry>('create_lorebook_entry', {
+      lorebookId,
+      data,
+    })
+  }
+
+  async updateLorebookEntry(
+    id: number,
+    data: Partial<LorebookEntryData> & { enabled?: boolean }
+  ): Promise<LorebookEntry> {
+    return await invoke<LorebookEntry>('update_lorebook_entry', { id, data })
+  }
+
+  async deleteLorebookEntry(id: number): Promise<LorebookEntry> {
+    return await invoke<LorebookEntry>('delete_lorebook_entry', { id })
+  }
+
+  // 设置学生的打字速度（字/分钟），传入null使用默认速度
+  async setStudentTypingSpeed(
+    studentId: number,
+    typingSpeed: number | null
+  ): Promise<void> {
+    await invoke('set_student_typing_speed', { studentId, typingSpeed })
+  }
+
+  async getUpcomingEvents(days?: number): Promise<CalendarEvent[]> {
+    return await invoke<CalendarEvent[]>('get_upcoming_events', { days })
+  }
+
+  async getAnniversaries(): Promise<Anniversary[]> {
+    return await invoke<Anniversary[]>('get_anniversaries')
+  }
+
+  async createAnniversary(data: AnniversaryData): Promise<Anniversary> {
+    return await invoke<Anniversary>('create_anniversary', { data })
+  }
+
+  async updateAnniversary(
+    id: number,
+    data: Partial<AnniversaryData>
+  ): Promise<Anniversary> {
+    return await invoke<Anniversary>('update_anniversary', { id, data })
+  }
+
+  async deleteAnniversary(id: number): Promise<Anniversary> {
+    return await invoke<Anniversary>('delete_anniversary', { id })
+  }
+
+  // 返回导出文件的路径
+  async exportCalendarIcs(): Promise<string> {
+    return await invoke<string>('export_calendar_ics')
+  }
+
+  async getPersonas(): Promise<Persona[]> {
+    return await invoke<Persona[]>('get_personas')
+  }
+
+  async createPersona(data: PersonaData): Promise<Persona> {
+    return await invoke<Persona>('create_persona', { data })
+  }
+
+  async updatePersona(
+    id: number,
+    data: Partial<PersonaData>
+  ): Promise<Persona> {
+    return await invoke<Persona>('update_persona', { id, data })
+  }
+
+  async deletePersona(id: number): Promise<Persona> {
+    return await invoke<Persona>('delete_persona', { id })
+  }
+
+  async setActivePersona(id: number | null): Promise<Persona | null> {
+    return await invoke<Persona | null>('set_active_persona', { id })
+  }
+
+  async setConversationPersona(
+    id: string,
+    personaId: number | null
+  ): Promise<Conversation> {
+    return await invoke<Conversation>('set_conversation_persona', {
+      id,
+      personaId,
+    })
+  }
+
+  async getStudentAvatarVariants(
+    studentId: number
+  ): Promise<{ index: number; url: string; label: string | null }[]> {
+    return await invoke('get_student_avatar_variants', { studentId })
+  }
+
+  async setConversationAvatar(
+    id: string,
+    avatar: string | null
+  ): Promise<Conversation> {
+    return await invoke<Conversation>('set_conversation_avatar', { id, avatar })
+  }
+
+  async prefetchAvatars(): Promise<{
+    total: number
+    downloaded: number
+    not_modified: number
+    failed: string[]
+  }> {
+    return await invoke('prefetch_avatars')
+  }
+
+  async debugLorePassages(
+    studentId: number,
+    query: string
+  ): Promise<LorePassage[]> {
+    return await invoke<LorePassage[]>('debug_lore_passages', {
+      studentId,
+      query,
+    })
+  }
 }
 
 export const tauriService = new TauriService()